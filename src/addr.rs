@@ -0,0 +1,141 @@
+use std::fmt;
+
+use super::error::slice;
+use super::Error;
+
+// LoROM maps each 32KiB chunk of the ROM to the upper half of a bank.
+pub const LOROM_BANK_SIZE: usize = 0x8000;
+
+// An address in the SNES CPU address space.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct SnesAddr(pub u32);
+
+// An offset into a ROM image with any copier header stripped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct FileOffset(pub usize);
+
+impl SnesAddr {
+    pub fn new(bank: u8, offset: u16) -> SnesAddr {
+        SnesAddr((u32::from(bank) << 16) | u32::from(offset))
+    }
+
+    pub fn bank(self) -> u8 {
+        (self.0 >> 16) as u8
+    }
+
+    pub fn offset(self) -> u16 {
+        self.0 as u16
+    }
+
+    // Returns `None` for addresses which don't map to ROM.  Banks 0x80-0xff
+    // mirror 0x00-0x7f.
+    pub fn to_file_offset(self) -> Option<FileOffset> {
+        let offset = self.offset() as usize;
+        if offset < LOROM_BANK_SIZE {
+            return None;
+        }
+        let bank = (self.bank() & 0x7f) as usize;
+        Some(FileOffset(
+            bank * LOROM_BANK_SIZE + offset - LOROM_BANK_SIZE,
+        ))
+    }
+}
+
+impl FileOffset {
+    pub fn to_snes_addr(self) -> SnesAddr {
+        let bank = (self.0 / LOROM_BANK_SIZE) as u8;
+        let offset = (self.0 % LOROM_BANK_SIZE + LOROM_BANK_SIZE) as u16;
+        SnesAddr::new(bank, offset)
+    }
+
+    pub fn bank(self) -> u8 {
+        self.to_snes_addr().bank()
+    }
+}
+
+impl fmt::Display for SnesAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "${:02x}:{:04x}", self.bank(), self.offset())
+    }
+}
+
+impl fmt::Display for FileOffset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#x}", self.0)
+    }
+}
+
+fn to_file_offset(addr: SnesAddr, table: &'static str, at: usize) -> Result<FileOffset, Error> {
+    addr.to_file_offset().ok_or(Error::BadPointer {
+        table,
+        offset: at,
+        addr: addr.0,
+    })
+}
+
+// Reads a little endian 16 bit pointer at `at` which points into `bank`.
+pub fn read_ptr16(
+    data: &[u8],
+    at: usize,
+    bank: u8,
+    table: &'static str,
+) -> Result<FileOffset, Error> {
+    let ptr = slice(data, table, at, 2)?;
+    let offset = u16::from(ptr[0]) | (u16::from(ptr[1]) << 8);
+    to_file_offset(SnesAddr::new(bank, offset), table, at)
+}
+
+// Reads a little endian 24 bit pointer at `at`.
+pub fn read_ptr24(data: &[u8], at: usize, table: &'static str) -> Result<FileOffset, Error> {
+    let ptr = slice(data, table, at, 3)?;
+    let addr = u32::from(ptr[0]) | (u32::from(ptr[1]) << 8) | (u32::from(ptr[2]) << 16);
+    to_file_offset(SnesAddr(addr), table, at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lorom_test() {
+        let addr = SnesAddr::new(0x0e, 0xa860);
+        assert_eq!(0x0e, addr.bank());
+        assert_eq!(0xa860, addr.offset());
+        assert_eq!(Some(FileOffset(0x72860)), addr.to_file_offset());
+        assert_eq!(addr, FileOffset(0x72860).to_snes_addr());
+        assert_eq!(0x0e, FileOffset(0x72860).bank());
+
+        // FastROM mirror.
+        assert_eq!(
+            Some(FileOffset(0x72860)),
+            SnesAddr::new(0x8e, 0xa860).to_file_offset()
+        );
+        // Low half of the bank isn't ROM.
+        assert_eq!(None, SnesAddr::new(0x0e, 0x2860).to_file_offset());
+
+        assert_eq!("$0e:a860", format!("{}", addr));
+    }
+
+    #[test]
+    fn read_ptr_test() {
+        let data = [0x60, 0xa8, 0x0e, 0x00, 0x10];
+        assert_eq!(Ok(FileOffset(0x72860)), read_ptr16(&data, 0, 0x0e, "test"));
+        assert_eq!(Ok(FileOffset(0x72860)), read_ptr24(&data, 0, "test"));
+        assert_eq!(
+            Err(Error::BadPointer {
+                table: "test",
+                offset: 3,
+                addr: 0x0e1000,
+            }),
+            read_ptr16(&data, 3, 0x0e, "test")
+        );
+        assert_eq!(
+            Err(Error::TruncatedRom {
+                table: "test",
+                offset: 4,
+                len: 2,
+            }),
+            read_ptr16(&data, 4, 0x0e, "test")
+        );
+    }
+}
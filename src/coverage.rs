@@ -0,0 +1,267 @@
+use super::addr::{FileOffset, LOROM_BANK_SIZE};
+use super::dialogue::Dialogue;
+use super::monster::{ai::CONDITION_LEN, MONSTER_NAME_LEN};
+use super::region::Region;
+use super::rom_info;
+use super::{Error, Ff4};
+
+// Tracks which bytes of a ROM are accounted for by a parsed structure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Coverage {
+    covered: Vec<bool>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct BankCoverage {
+    pub bank: u8,
+    pub covered: usize,
+    pub len: usize,
+}
+
+impl BankCoverage {
+    pub fn percent(&self) -> f64 {
+        percent(self.covered, self.len)
+    }
+}
+
+fn percent(covered: usize, len: usize) -> f64 {
+    if len == 0 {
+        return 0.0;
+    }
+    covered as f64 * 100.0 / len as f64
+}
+
+impl Coverage {
+    pub fn new(len: usize) -> Coverage {
+        Coverage {
+            covered: vec![false; len],
+        }
+    }
+
+    // Marks the bytes of `region`.  Anything past the end of the ROM is
+    // ignored.
+    pub fn mark(&mut self, region: Region) {
+        let end = region.end().min(self.covered.len());
+        let start = region.start.min(end);
+        for b in &mut self.covered[start..end] {
+            *b = true;
+        }
+    }
+
+    pub fn is_covered(&self, offset: usize) -> bool {
+        self.covered.get(offset).cloned().unwrap_or(false)
+    }
+
+    pub fn covered(&self) -> usize {
+        self.covered.iter().filter(|&&b| b).count()
+    }
+
+    pub fn percent(&self) -> f64 {
+        percent(self.covered(), self.covered.len())
+    }
+
+    pub fn banks(&self) -> Vec<BankCoverage> {
+        self.covered
+            .chunks(LOROM_BANK_SIZE)
+            .enumerate()
+            .map(|(i, bank)| BankCoverage {
+                bank: FileOffset(i * LOROM_BANK_SIZE).bank(),
+                covered: bank.iter().filter(|&&b| b).count(),
+                len: bank.len(),
+            })
+            .collect()
+    }
+
+    // Runs of bytes which nothing has claimed.
+    pub fn uncovered(&self) -> Vec<Region> {
+        let mut regions = Vec::new();
+        let mut start = None;
+        for (offset, &covered) in self.covered.iter().enumerate() {
+            match (start, covered) {
+                (None, false) => start = Some(offset),
+                (Some(s), true) => {
+                    regions.push(Region::new("uncovered", s, offset - s));
+                    start = None;
+                }
+                _ => (),
+            }
+        }
+        if let Some(s) = start {
+            regions.push(Region::new("uncovered", s, self.covered.len() - s));
+        }
+
+        regions
+    }
+}
+
+// Marks every byte the parsers consumed while building `ff4` and `dialogue`
+// from `data`.  Tables are marked by what was actually parsed rather than by
+// their region so a parser which stops early leaves the rest of its region
+// uncovered.
+pub fn coverage(data: &[u8], ff4: &Ff4, dialogue: Option<&Dialogue>) -> Result<Coverage, Error> {
+    let mut coverage = Coverage::new(data.len());
+    let monster_data = &ff4.monster_data;
+    let map = &monster_data.map;
+    let mark_from = |coverage: &mut Coverage, region: Region, len: usize| {
+        coverage.mark(Region::new(region.name, region.start, len))
+    };
+
+    coverage.mark(Region::new(
+        "internal header",
+        rom_info::HEADER_START,
+        rom_info::HEADER_LEN,
+    ));
+
+    mark_from(
+        &mut coverage,
+        map.monster_names(),
+        monster_data.name_table.len() * MONSTER_NAME_LEN,
+    );
+    mark_from(
+        &mut coverage,
+        map.monster_gp(),
+        monster_data.gp_table.len() * 2,
+    );
+    mark_from(
+        &mut coverage,
+        map.monster_xp(),
+        monster_data.xp_table.len() * 2,
+    );
+    mark_from(
+        &mut coverage,
+        map.monster_offsets(),
+        monster_data.monsters.len() * 2,
+    );
+    mark_from(
+        &mut coverage,
+        map.monster_stats(),
+        monster_data.stat_table.len() * 3,
+    );
+    mark_from(
+        &mut coverage,
+        map.monster_speeds(),
+        monster_data.speed_table.len() * 2,
+    );
+    mark_from(
+        &mut coverage,
+        map.monster_drops(),
+        monster_data.drop_tables.len() * 4,
+    );
+
+    for monster in &monster_data.monsters {
+        coverage.mark(Region::new(
+            "monster records",
+            monster.span.offset,
+            monster.span.len,
+        ));
+    }
+
+    let ai = &monster_data.ai;
+    mark_from(
+        &mut coverage,
+        map.attack_groups(),
+        ai.groups.iter().map(|g| g.size()).sum(),
+    );
+    mark_from(
+        &mut coverage,
+        map.condition_sets(),
+        ai.condition_sets.iter().map(|s| s.size()).sum(),
+    );
+    mark_from(
+        &mut coverage,
+        map.conditions(),
+        ai.conditions.len() * CONDITION_LEN,
+    );
+    mark_from(
+        &mut coverage,
+        map.earth_scripts(),
+        ai.earth_scripts.iter().map(|s| s.size()).sum(),
+    );
+    mark_from(
+        &mut coverage,
+        map.moon_scripts(),
+        ai.moon_scripts.iter().map(|s| s.size()).sum(),
+    );
+
+    mark_from(
+        &mut coverage,
+        map.battle_message_pointers(),
+        ff4.messages.len() * 2,
+    );
+    for message in &ff4.messages {
+        coverage.mark(Region::new(
+            "battle messages",
+            message.span.offset,
+            message.span.len,
+        ));
+    }
+
+    if let Some(dialogue) = dialogue {
+        let pointers = [map.dialogue_bank1_pointers(), map.dialogue_bank2_pointers()];
+        for (pointers, lines) in pointers.iter().zip(&dialogue.banks) {
+            mark_from(&mut coverage, *pointers, lines.len() * 2);
+            for line in lines {
+                coverage.mark(Region::new("dialogue", line.span.offset, line.span.len));
+            }
+        }
+    }
+
+    Ok(coverage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_utils;
+    use super::*;
+
+    #[test]
+    fn coverage_test() {
+        let mut coverage = Coverage::new(LOROM_BANK_SIZE * 2);
+        coverage.mark(Region::new("a", 0x10, 0x10));
+        coverage.mark(Region::new("b", 0x18, 0x10));
+        coverage.mark(Region::new("c", LOROM_BANK_SIZE * 2 - 4, 0x10));
+
+        assert!(!coverage.is_covered(0x0f));
+        assert!(coverage.is_covered(0x10));
+        assert!(coverage.is_covered(0x27));
+        assert!(!coverage.is_covered(0x28));
+        assert_eq!(0x1c, coverage.covered());
+        assert_eq!(
+            vec![
+                BankCoverage {
+                    bank: 0,
+                    covered: 0x18,
+                    len: LOROM_BANK_SIZE,
+                },
+                BankCoverage {
+                    bank: 1,
+                    covered: 4,
+                    len: LOROM_BANK_SIZE,
+                },
+            ],
+            coverage.banks()
+        );
+        assert_eq!(
+            vec![
+                Region::new("uncovered", 0, 0x10),
+                Region::new("uncovered", 0x28, LOROM_BANK_SIZE * 2 - 4 - 0x28),
+            ],
+            coverage.uncovered()
+        );
+    }
+
+    #[cfg_attr(feature = "ci_tests", ignore)]
+    #[test]
+    fn coverage_rom_test() {
+        let data = test_utils::load_rom().unwrap();
+        let ff4 = super::super::parse_rom(&data).unwrap();
+        let dialogue = ff4.dialogue(&data).unwrap();
+        let coverage = coverage(&data, &ff4, Some(&dialogue)).unwrap();
+
+        let map = &ff4.monster_data.map;
+        for region in map.regions() {
+            assert!(coverage.is_covered(region.start), "{}", region);
+        }
+        assert!(coverage.percent() > 0.0);
+    }
+}
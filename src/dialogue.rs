@@ -0,0 +1,358 @@
+use super::addr::read_ptr16;
+use super::region::Region;
+use super::rom_map::RomMap;
+use super::span::Span;
+use super::string::CharTable;
+use super::Error;
+
+// Control codes.  Anything else is text.  These, and the dialogue bank
+// offsets in `RomMap::us`, haven't been checked against the game's text
+// routine.  `parse_rom_test` checks a known line and that each code turns up
+// in the retail text, and dialogue is parsed separately from `parse_rom` so a
+// wrong guess here doesn't stop the rest of the ROM loading.
+const END: u8 = 0x00;
+const NEWLINE: u8 = 0x01;
+const CHARACTER_NAME: u8 = 0x02;
+const MUSIC: u8 = 0x03;
+const PAUSE: u8 = 0x04;
+const SOUND: u8 = 0x05;
+const ITEM: u8 = 0x06;
+const NUMBER: u8 = 0x07;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum TextSegment {
+    Text(String),
+    Newline,
+    // Waits for `frames` before printing the rest of the line.
+    Pause { frames: u8 },
+    // The current name of party member `index`.
+    CharacterName { index: u8 },
+    // The item and number set by the event which shows the text.
+    Item,
+    Number,
+    Music { song: u8 },
+    Sound { effect: u8 },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Line {
+    pub segments: Vec<TextSegment>,
+    // Includes the end code.
+    pub span: Span,
+}
+
+impl Line {
+    pub fn plain_text(&self) -> String {
+        render(&self.segments)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Dialogue {
+    pub banks: Vec<Vec<Line>>,
+}
+
+// Renders `segments` as plain text.  Substitutions become `<name xx>`,
+// `<item>` and `<number>`; pauses and sound cues are dropped.
+pub fn render(segments: &[TextSegment]) -> String {
+    let mut s = String::new();
+    for segment in segments {
+        match segment {
+            TextSegment::Text(text) => s.push_str(text),
+            TextSegment::Newline => s.push('\n'),
+            TextSegment::CharacterName { index } => s.push_str(&format!("<name {:02x}>", index)),
+            TextSegment::Item => s.push_str("<item>"),
+            TextSegment::Number => s.push_str("<number>"),
+            TextSegment::Pause { .. } | TextSegment::Music { .. } | TextSegment::Sound { .. } => {}
+        }
+    }
+    s
+}
+
+// Parses one line from the start of `data` up to and including its end code.
+// Returns the segments and the number of bytes used.  Text is decoded for
+// display with DTE pairs expanded, so bytes the table doesn't know are an
+// error.  Errors report offsets relative to `data`.
+pub fn parse_line(data: &[u8], char_table: &CharTable) -> Result<(Vec<TextSegment>, usize), Error> {
+    let mut segments = Vec::new();
+    let arg = |offset: usize| {
+        data.get(offset + 1).cloned().ok_or(Error::TruncatedRom {
+            table: "text",
+            offset: offset + 1,
+            len: 1,
+        })
+    };
+
+    let mut offset = 0;
+    loop {
+        let code = match data.get(offset) {
+            Some(&code) => code,
+            None => {
+                return Err(Error::TruncatedRom {
+                    table: "text",
+                    offset,
+                    len: 1,
+                })
+            }
+        };
+        let (segment, len) = match code {
+            END => return Ok((segments, offset + 1)),
+            NEWLINE => (TextSegment::Newline, 1),
+            CHARACTER_NAME => (
+                TextSegment::CharacterName {
+                    index: arg(offset)?,
+                },
+                2,
+            ),
+            MUSIC => (TextSegment::Music { song: arg(offset)? }, 2),
+            PAUSE => (
+                TextSegment::Pause {
+                    frames: arg(offset)?,
+                },
+                2,
+            ),
+            SOUND => (
+                TextSegment::Sound {
+                    effect: arg(offset)?,
+                },
+                2,
+            ),
+            ITEM => (TextSegment::Item, 1),
+            NUMBER => (TextSegment::Number, 1),
+            _ => {
+                // Kanji page bytes take the next byte with them even if it
+                // looks like a control code.
+                let mut end = offset;
+                while end < data.len() && data[end] > NUMBER {
+                    end += if char_table.is_page(data[end]) { 2 } else { 1 };
+                }
+                let end = end.min(data.len());
+                let text = char_table
+                    .decode(&data[offset..end])
+                    .map_err(|e| e.relocate("text", offset))?;
+                (TextSegment::Text(text), end - offset)
+            }
+        };
+        segments.push(segment);
+        offset += len;
+    }
+}
+
+// Reads a table of 16 bit pointers into `bank` and checks each one points
+// into `text`.  Returns the ROM offset of each entry.
+pub(crate) fn read_text_pointers(
+    data: &[u8],
+    pointers: Region,
+    bank: u8,
+    text: Region,
+) -> Result<Vec<usize>, Error> {
+    let mut starts = Vec::new();
+    for at in pointers.offsets(2) {
+        let start = read_ptr16(data, at, bank, pointers.name)?;
+        if !text.contains(start.0) {
+            return Err(Error::BadPointer {
+                table: pointers.name,
+                offset: at,
+                addr: start.to_snes_addr().0,
+            });
+        }
+        starts.push(start.0);
+    }
+
+    Ok(starts)
+}
+
+fn parse_bank(
+    data: &[u8],
+    pointers: Region,
+    bank: u8,
+    text: Region,
+    char_table: &CharTable,
+) -> Result<Vec<Line>, Error> {
+    let text_data = text.slice(data)?;
+
+    let mut lines = Vec::new();
+    for start in read_text_pointers(data, pointers, bank, text)? {
+        let (segments, len) = parse_line(&text_data[start - text.start..], char_table)
+            .map_err(|e| e.relocate(text.name, start))?;
+        lines.push(Line {
+            segments,
+            span: Span::new(start, len),
+        });
+    }
+
+    Ok(lines)
+}
+
+pub fn parse(data: &[u8], map: &RomMap, char_table: &CharTable) -> Result<Dialogue, Error> {
+    let banks = vec![
+        parse_bank(
+            data,
+            map.dialogue_bank1_pointers(),
+            map.dialogue_bank1_bank,
+            map.dialogue_bank1(),
+            char_table,
+        )?,
+        parse_bank(
+            data,
+            map.dialogue_bank2_pointers(),
+            map.dialogue_bank2_bank,
+            map.dialogue_bank2(),
+            char_table,
+        )?,
+    ];
+
+    Ok(Dialogue { banks })
+}
+
+// Points every line of both banks at a single empty line.
+#[cfg(test)]
+pub(crate) fn write_empty_dialogue(data: &mut [u8], map: &RomMap) {
+    let banks = [
+        (map.dialogue_bank1_pointers(), map.dialogue_bank1()),
+        (map.dialogue_bank2_pointers(), map.dialogue_bank2()),
+    ];
+    for (pointers, text) in banks.iter() {
+        let offset = super::addr::FileOffset(text.start).to_snes_addr().offset();
+        for at in pointers.offsets(2) {
+            data[at] = offset as u8;
+            data[at + 1] = (offset >> 8) as u8;
+        }
+        data[text.start] = END;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_utils;
+    use super::*;
+
+    #[test]
+    fn parse_line_test() {
+        let table = CharTable::us();
+        // "Hi <name 00>!\n" with a pause, a sound and a DTE pair.
+        let data = [
+            0x49, 0x64, 0xff, 0x02, 0x00, 0xc4, 0x01, 0x04, 0x10, 0x05, 0x01, 0x06, 0x8b, 0x07,
+            0x03, 0x2a, 0x00, 0x42,
+        ];
+        let (segments, len) = parse_line(&data, &table).unwrap();
+        assert_eq!(17, len);
+        assert_eq!(
+            vec![
+                TextSegment::Text("Hi ".to_string()),
+                TextSegment::CharacterName { index: 0 },
+                TextSegment::Text("!".to_string()),
+                TextSegment::Newline,
+                TextSegment::Pause { frames: 0x10 },
+                TextSegment::Sound { effect: 0x01 },
+                TextSegment::Item,
+                TextSegment::Text("th".to_string()),
+                TextSegment::Number,
+                TextSegment::Music { song: 0x2a },
+            ],
+            segments
+        );
+        assert_eq!("Hi <name 00>!\n<item>th<number>", render(&segments));
+
+        assert_eq!(
+            Err(Error::UnknownTextByte {
+                table: "text",
+                offset: 2,
+                byte: 0x1d,
+            }),
+            parse_line(&[0x01, 0x49, 0x1d, 0x00], &table)
+        );
+
+        assert_eq!(
+            Err(Error::TruncatedRom {
+                table: "text",
+                offset: 2,
+                len: 1,
+            }),
+            parse_line(&[0x49, 0x02], &table)
+        );
+        assert_eq!(
+            Err(Error::TruncatedRom {
+                table: "text",
+                offset: 1,
+                len: 1,
+            }),
+            parse_line(&[0x49], &table)
+        );
+
+        // The second byte of a kanji isn't a control code.
+        let kanji = CharTable::from_tbl("1E02=剣\n").unwrap();
+        assert_eq!(
+            (vec![TextSegment::Text("剣".to_string())], 3),
+            parse_line(&[0x1e, 0x02, 0x00], &kanji).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_test() {
+        let map = RomMap::us();
+        let mut data = vec![0x00; 0x100000];
+        write_empty_dialogue(&mut data, &map);
+        let text = map.dialogue_bank2_text + 1;
+        data[text..text + 3].copy_from_slice(&[0x42, 0x01, 0x00]);
+        data[map.dialogue_bank2_pointers + 2] += 1;
+
+        let dialogue = parse(&data, &map, &CharTable::us()).unwrap();
+        assert_eq!(2, dialogue.banks.len());
+        assert_eq!(0x100, dialogue.banks[0].len());
+        assert_eq!("", dialogue.banks[0][0].plain_text());
+        assert_eq!("A\n", dialogue.banks[1][1].plain_text());
+        assert_eq!(Span::new(text, 3), dialogue.banks[1][1].span);
+
+        // A line which runs off the end of the ROM.
+        data.truncate(text + 2);
+        assert_eq!(
+            Err(Error::TruncatedRom {
+                table: "dialogue bank 2",
+                offset: map.dialogue_bank2_text,
+                len: map.dialogue_bank2_text_len,
+            }),
+            parse(&data, &map, &CharTable::us()).map(|_| ())
+        );
+    }
+
+    #[cfg_attr(feature = "ci_tests", ignore)]
+    #[test]
+    fn parse_rom_test() {
+        let data = test_utils::load_rom().unwrap();
+        let dialogue = parse(&data, &RomMap::us(), &CharTable::us()).unwrap();
+
+        // Tellah's line to Edward.  Most of it is written with DTE pairs.
+        let line = "You spoony bard!";
+        assert!(
+            dialogue
+                .banks
+                .iter()
+                .flatten()
+                .any(|l| l.plain_text().contains(line)),
+            "{:?} not found",
+            line
+        );
+
+        // Every control code is used somewhere in the retail text.
+        let segments: Vec<_> = dialogue
+            .banks
+            .iter()
+            .flatten()
+            .flat_map(|l| l.segments.iter())
+            .collect();
+        let used = |kind: fn(&TextSegment) -> bool, name: &str| {
+            assert!(segments.iter().any(|s| kind(s)), "no {} found", name);
+        };
+        used(|s| *s == TextSegment::Newline, "newline");
+        used(
+            |s| matches!(s, TextSegment::CharacterName { .. }),
+            "character name",
+        );
+        used(|s| matches!(s, TextSegment::Pause { .. }), "pause");
+        used(|s| *s == TextSegment::Item, "item");
+        used(|s| *s == TextSegment::Number, "number");
+        used(|s| matches!(s, TextSegment::Music { .. }), "music");
+        used(|s| matches!(s, TextSegment::Sound { .. }), "sound");
+    }
+}
@@ -0,0 +1,418 @@
+use nom::types::CompleteByteSlice;
+use std::fmt;
+
+// Errors carry the name of the table being processed and the absolute ROM
+// offset of the offending byte.  Functions which work on a bare slice (like
+// `string::decode`) report offsets relative to that slice; callers use
+// `relocate` to turn them into ROM offsets.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    // The ROM ended before `len` bytes could be read at `offset`.
+    TruncatedRom {
+        table: &'static str,
+        offset: usize,
+        len: usize,
+    },
+    UnknownTextByte {
+        table: &'static str,
+        offset: usize,
+        byte: u8,
+    },
+    UnencodableChar {
+        table: &'static str,
+        offset: usize,
+        c: char,
+    },
+    // `field` holds `value`, which its bits in the ROM can't represent.
+    UnencodableValue {
+        table: &'static str,
+        offset: usize,
+        field: &'static str,
+        value: usize,
+    },
+    BadScriptOpcode {
+        table: &'static str,
+        offset: usize,
+        opcode: u8,
+    },
+    // Data needs `len` bytes or entries but the table only holds `capacity`.
+    TableOverflow {
+        table: &'static str,
+        offset: usize,
+        len: usize,
+        capacity: usize,
+    },
+    TableSizeMismatch {
+        table: &'static str,
+        offset: usize,
+        expected: usize,
+        found: usize,
+    },
+    IndexOutOfRange {
+        table: &'static str,
+        offset: usize,
+        index: usize,
+        len: usize,
+    },
+    // A pointer read at `offset` doesn't point into the ROM.
+    BadPointer {
+        table: &'static str,
+        offset: usize,
+        addr: u32,
+    },
+    // Data at `offset` is outside `bank`, so the 16 bit pointers into that
+    // bank which refer to it can't reach it.
+    WrongBank {
+        table: &'static str,
+        offset: usize,
+        bank: u8,
+    },
+    // The region at `offset` shares bytes with the `other` region.
+    RegionOverlap {
+        table: &'static str,
+        offset: usize,
+        other: &'static str,
+    },
+    // The internal header doesn't match a release `parse_rom` handles.
+    UnsupportedRom {
+        table: &'static str,
+        offset: usize,
+        title: String,
+    },
+    // Line `line` of a `.tbl` file, which starts at `offset`, isn't an entry.
+    BadTblEntry {
+        table: &'static str,
+        offset: usize,
+        line: usize,
+    },
+}
+
+impl Error {
+    pub fn table(&self) -> &'static str {
+        match *self {
+            Error::TruncatedRom { table, .. }
+            | Error::UnknownTextByte { table, .. }
+            | Error::UnencodableChar { table, .. }
+            | Error::UnencodableValue { table, .. }
+            | Error::BadScriptOpcode { table, .. }
+            | Error::TableOverflow { table, .. }
+            | Error::TableSizeMismatch { table, .. }
+            | Error::IndexOutOfRange { table, .. }
+            | Error::BadPointer { table, .. }
+            | Error::WrongBank { table, .. }
+            | Error::RegionOverlap { table, .. }
+            | Error::UnsupportedRom { table, .. }
+            | Error::BadTblEntry { table, .. } => table,
+        }
+    }
+
+    pub fn offset(&self) -> usize {
+        match *self {
+            Error::TruncatedRom { offset, .. }
+            | Error::UnknownTextByte { offset, .. }
+            | Error::UnencodableChar { offset, .. }
+            | Error::UnencodableValue { offset, .. }
+            | Error::BadScriptOpcode { offset, .. }
+            | Error::TableOverflow { offset, .. }
+            | Error::TableSizeMismatch { offset, .. }
+            | Error::IndexOutOfRange { offset, .. }
+            | Error::BadPointer { offset, .. }
+            | Error::WrongBank { offset, .. }
+            | Error::RegionOverlap { offset, .. }
+            | Error::UnsupportedRom { offset, .. }
+            | Error::BadTblEntry { offset, .. } => offset,
+        }
+    }
+
+    // Renames the table and moves the offset by `base`.
+    pub fn relocate(mut self, new_table: &'static str, base: usize) -> Error {
+        match self {
+            Error::TruncatedRom {
+                ref mut table,
+                ref mut offset,
+                ..
+            }
+            | Error::UnknownTextByte {
+                ref mut table,
+                ref mut offset,
+                ..
+            }
+            | Error::UnencodableChar {
+                ref mut table,
+                ref mut offset,
+                ..
+            }
+            | Error::UnencodableValue {
+                ref mut table,
+                ref mut offset,
+                ..
+            }
+            | Error::BadScriptOpcode {
+                ref mut table,
+                ref mut offset,
+                ..
+            }
+            | Error::TableOverflow {
+                ref mut table,
+                ref mut offset,
+                ..
+            }
+            | Error::TableSizeMismatch {
+                ref mut table,
+                ref mut offset,
+                ..
+            }
+            | Error::IndexOutOfRange {
+                ref mut table,
+                ref mut offset,
+                ..
+            }
+            | Error::BadPointer {
+                ref mut table,
+                ref mut offset,
+                ..
+            }
+            | Error::WrongBank {
+                ref mut table,
+                ref mut offset,
+                ..
+            }
+            | Error::RegionOverlap {
+                ref mut table,
+                ref mut offset,
+                ..
+            }
+            | Error::UnsupportedRom {
+                ref mut table,
+                ref mut offset,
+                ..
+            }
+            | Error::BadTblEntry {
+                ref mut table,
+                ref mut offset,
+                ..
+            } => {
+                *table = new_table;
+                *offset += base;
+            }
+        }
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::TruncatedRom { table, offset, len } => write!(
+                f,
+                "{}: ROM truncated reading {} bytes at {:#x}",
+                table, len, offset
+            ),
+            Error::UnknownTextByte {
+                table,
+                offset,
+                byte,
+            } => write!(
+                f,
+                "{}: unknown text byte {:#04x} at {:#x}",
+                table, byte, offset
+            ),
+            Error::UnencodableChar { table, offset, c } => {
+                write!(f, "{}: can't encode {:?} at {:#x}", table, c, offset)
+            }
+            Error::UnencodableValue {
+                table,
+                offset,
+                field,
+                value,
+            } => write!(
+                f,
+                "{}: can't encode {} {} at {:#x}",
+                table, field, value, offset
+            ),
+            Error::BadScriptOpcode {
+                table,
+                offset,
+                opcode,
+            } => write!(
+                f,
+                "{}: bad script opcode {:#04x} at {:#x}",
+                table, opcode, offset
+            ),
+            Error::TableOverflow {
+                table,
+                offset,
+                len,
+                capacity,
+            } => write!(
+                f,
+                "{}: {:#x} needed but only {:#x} fit at {:#x}",
+                table, len, capacity, offset
+            ),
+            Error::TableSizeMismatch {
+                table,
+                offset,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{}: expected {} entries, found {} at {:#x}",
+                table, expected, found, offset
+            ),
+            Error::IndexOutOfRange {
+                table,
+                offset,
+                index,
+                len,
+            } => write!(
+                f,
+                "{}: index {:#x} out of range ({} entries) at {:#x}",
+                table, index, len, offset
+            ),
+            Error::BadPointer {
+                table,
+                offset,
+                addr,
+            } => write!(
+                f,
+                "{}: pointer to {:#08x} at {:#x} is outside the ROM",
+                table, addr, offset
+            ),
+            Error::WrongBank {
+                table,
+                offset,
+                bank,
+            } => write!(f, "{}: {:#x} isn't in bank {:#04x}", table, offset, bank),
+            Error::RegionOverlap {
+                table,
+                offset,
+                other,
+            } => write!(f, "{}: overlaps {} at {:#x}", table, other, offset),
+            Error::UnsupportedRom {
+                table,
+                offset,
+                title,
+            } => write!(f, "{}: unsupported ROM {:?} at {:#x}", table, title, offset),
+            Error::BadTblEntry {
+                table,
+                offset,
+                line,
+            } => write!(f, "{}: bad entry on line {} at {:#x}", table, line, offset),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// Returns `len` bytes of `data` starting at `offset` or a `TruncatedRom`
+// error if the ROM is too short.
+pub(crate) fn slice<'a>(
+    data: &'a [u8],
+    table: &'static str,
+    offset: usize,
+    len: usize,
+) -> Result<&'a [u8], Error> {
+    data.get(offset..offset + len)
+        .ok_or(Error::TruncatedRom { table, offset, len })
+}
+
+// Converts a nom error from parsing `data`, which starts at ROM offset
+// `base`, into a `TruncatedRom` error pointing at the byte where parsing
+// stopped.  The table parsers only fail when an entry runs past the end of
+// its data; parsers which can reject bytes for other reasons (like
+// `script::parse`) map those failures themselves.
+pub(crate) fn from_nom(
+    e: nom::Err<CompleteByteSlice>,
+    data: &[u8],
+    table: &'static str,
+    base: usize,
+) -> Error {
+    match e {
+        nom::Err::Error(nom::Context::Code(rest, _))
+        | nom::Err::Failure(nom::Context::Code(rest, _)) => Error::TruncatedRom {
+            table,
+            offset: base + data.len() - rest.len(),
+            len: rest.len() + 1,
+        },
+        nom::Err::Incomplete(needed) => Error::TruncatedRom {
+            table,
+            offset: base + data.len(),
+            len: match needed {
+                nom::Needed::Size(len) => len,
+                nom::Needed::Unknown => 1,
+            },
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relocate_test() {
+        let e = Error::UnknownTextByte {
+            table: "text",
+            offset: 3,
+            byte: 0x00,
+        }
+        .relocate("monster names", 0x71800);
+        assert_eq!("monster names", e.table());
+        assert_eq!(0x71803, e.offset());
+        assert_eq!(
+            "monster names: unknown text byte 0x00 at 0x71803",
+            format!("{}", e)
+        );
+    }
+
+    #[test]
+    fn slice_test() {
+        let data = [0x00, 0x01, 0x02];
+        assert_eq!(Ok(&data[1..3]), slice(&data, "test", 1, 2));
+        assert_eq!(
+            Err(Error::TruncatedRom {
+                table: "test",
+                offset: 2,
+                len: 2,
+            }),
+            slice(&data, "test", 2, 2)
+        );
+    }
+
+    #[test]
+    fn from_nom_test() {
+        let data = [0x01, 0x02, 0xf6];
+        assert_eq!(
+            Error::TruncatedRom {
+                table: "conditions",
+                offset: 0x100 + 2,
+                len: 2,
+            },
+            from_nom(
+                nom::Err::Error(nom::Context::Code(
+                    CompleteByteSlice(&data[2..]),
+                    nom::ErrorKind::Many1
+                )),
+                &data,
+                "conditions",
+                0x100
+            )
+        );
+        assert_eq!(
+            Error::TruncatedRom {
+                table: "conditions",
+                offset: 0x100 + 3,
+                len: 1,
+            },
+            from_nom(
+                nom::Err::Error(nom::Context::Code(
+                    CompleteByteSlice(&data[3..]),
+                    nom::ErrorKind::Many1
+                )),
+                &data,
+                "conditions",
+                0x100
+            )
+        );
+    }
+}
@@ -1,6 +1,3 @@
-#[macro_use]
-extern crate simple_error;
-
 #[macro_use]
 extern crate lazy_static;
 
@@ -10,21 +7,96 @@ extern crate nom;
 #[macro_use]
 extern crate serde_derive;
 
-use std::error::Error;
-
+pub mod addr;
+pub mod coverage;
+pub mod dialogue;
+mod error;
 pub mod monster;
+pub mod region;
+pub mod rom;
+pub mod rom_info;
 pub mod rom_map;
+pub mod span;
 pub mod string;
 pub mod test_utils;
 
+pub use crate::error::Error;
+
 pub struct Ff4 {
+    pub info: rom_info::RomInfo,
     pub monster_data: monster::MonsterData,
+    // Battle messages indexed by `Action::ShowMessage`, decoded losslessly.
+    pub messages: Vec<span::Spanned<String>>,
+}
+
+impl Ff4 {
+    // Parses the dialogue banks of `data`, the ROM `self` was parsed from.
+    // This isn't part of `parse_rom` since the dialogue map and control codes
+    // are still unconfirmed.
+    pub fn dialogue(&self, data: &[u8]) -> Result<dialogue::Dialogue, Error> {
+        dialogue::parse(data, &self.monster_data.map, &self.monster_data.char_table)
+    }
+
+    // Battle message `index` for display, with DTE pairs expanded.
+    pub fn message_text(&self, index: u8) -> Option<String> {
+        let char_table = &self.monster_data.char_table;
+        let message = self.messages.get(index as usize)?;
+        let bytes = char_table.encode_lossless(&message.value).ok()?;
+        Some(char_table.decode_display(&bytes))
+    }
+}
+
+// Parses a ROM with any copier header stripped.  ROMs which aren't a
+// supported release are refused rather than decoded with the wrong offsets.
+// Only the US release has a verified map and character table so far.
+pub fn parse_rom(data: &[u8]) -> Result<Ff4, Error> {
+    let info = rom_info::RomInfo::parse(data)?;
+    let supported = info.release().and_then(|release| {
+        Some((
+            rom_map::RomMap::for_release(release)?,
+            string::CharTable::for_release(release)?,
+        ))
+    });
+    let (map, char_table) = match supported {
+        Some(supported) => supported,
+        None => {
+            return Err(Error::UnsupportedRom {
+                table: "internal header",
+                offset: rom_info::HEADER_START,
+                title: info.title,
+            })
+        }
+    };
+
+    parse(data, info, &map, &char_table)
+}
+
+// Parses a ROM using `map` rather than one picked from the internal header.
+// This lets ROM hacks which moved tables be parsed with a custom map.  Text
+// still uses the release's encoding, or the US one if the release has none
+// or the header isn't recognized.
+pub fn parse_rom_with_map(data: &[u8], map: &rom_map::RomMap) -> Result<Ff4, Error> {
+    let info = rom_info::RomInfo::parse(data)?;
+    let char_table = info
+        .release()
+        .and_then(string::CharTable::for_release)
+        .unwrap_or_else(string::CharTable::us);
+    parse(data, info, map, &char_table)
 }
 
-pub fn parse_rom(data: &[u8]) -> Result<Ff4, Box<Error>> {
-    let monster_data = monster::parse(data)?;
+fn parse(
+    data: &[u8],
+    info: rom_info::RomInfo,
+    map: &rom_map::RomMap,
+    char_table: &string::CharTable,
+) -> Result<Ff4, Error> {
+    map.validate()?;
+    let monster_data = monster::parse(data, map, char_table)?;
+    let messages = monster::ai::parse_messages(data, map, char_table)?;
 
     Ok(Ff4 {
-        monster_data: monster_data,
+        info,
+        monster_data,
+        messages,
     })
 }
@@ -1,56 +1,141 @@
 extern crate ff4;
-#[macro_use]
-extern crate serde_derive;
 extern crate serde_json;
 
+use std::env;
 use std::error::Error;
 use std::fs::{create_dir_all, write};
 
-use ff4::monster::{DropTable, Monster, Speed, Stats};
+use ff4::coverage;
+use ff4::monster::asm;
+use ff4::rom_map::RomMap;
+use ff4::string::CharTable;
 use ff4::test_utils;
 
-// Aggregate all information about a given monster for easier printing.
-#[derive(Debug, Default, PartialEq, Serialize)]
-pub struct CombinedMonster {
-    pub monster: Monster,
-    pub xp: u16,
-    pub gp: u16,
-    pub physical_attack: Stats,
-    pub physical_defense: Stats,
-    pub magical_defense: Stats,
-    pub speed: Speed,
-    pub drop_table: DropTable,
-}
-
-fn dump_monsters(ff4: &ff4::Ff4) {
-    let dir = format!("out/monster");
-    create_dir_all(&dir).unwrap();
+fn dump_monsters(ff4: &ff4::Ff4) -> Result<(), Box<dyn Error>> {
+    let dir = "out/monster";
+    create_dir_all(dir)?;
     let monster_data = &ff4.monster_data;
     for i in 0..monster_data.monsters.len() {
-        let name = monster_data.name_table[i].trim();
-
-        let m = &monster_data.monsters[i];
-        let stat_table = &monster_data.stat_table;
-        let monster = CombinedMonster {
-            monster: m.clone(),
-            xp: monster_data.xp_table[i],
-            gp: monster_data.gp_table[i],
-            physical_attack: stat_table[m.physical_attack_index as usize].clone(),
-            physical_defense: stat_table[m.physical_defense_index as usize].clone(),
-            magical_defense: stat_table[m.magical_defense_index as usize].clone(),
-            speed: monster_data.speed_table[m.speed_index as usize].clone(),
-            drop_table: monster_data.drop_tables[m.drop_table_index as usize].clone(),
-        };
-        let j = serde_json::to_string_pretty(&monster).unwrap();
-        write(format!("{}/{}.json", &dir, name), &j).unwrap();
+        let monster = monster_data.resolved(i)?;
+        let j = serde_json::to_string_pretty(&monster)?;
+        write(format!("{}/{}.json", dir, monster.name), &j)?;
+    }
+
+    Ok(())
+}
+
+fn print_coverage(rom_data: &[u8], ff4: &ff4::Ff4) -> Result<(), Box<dyn Error>> {
+    // Dialogue is left out rather than failing the whole report.
+    let dialogue = match ff4.dialogue(rom_data) {
+        Ok(dialogue) => Some(dialogue),
+        Err(e) => {
+            eprintln!("dialogue not covered: {}", e);
+            None
+        }
+    };
+    let coverage = coverage::coverage(rom_data, ff4, dialogue.as_ref())?;
+    for bank in coverage.banks() {
+        println!(
+            "bank ${:02x}: {:6} / {:6} bytes ({:5.1}%)",
+            bank.bank,
+            bank.covered,
+            bank.len,
+            bank.percent()
+        );
+    }
+    println!("total: {:.1}%", coverage.percent());
+
+    println!("uncovered:");
+    for region in coverage.uncovered() {
+        println!(
+            "  {:#07x}-{:#07x} ({:#x} bytes)",
+            region.start,
+            region.end(),
+            region.len
+        );
     }
+
+    Ok(())
+}
+
+fn dump_dialogue(rom_data: &[u8], ff4: &ff4::Ff4) -> Result<(), Box<dyn Error>> {
+    let dir = "out/dialogue";
+    create_dir_all(dir)?;
+    for (i, lines) in ff4.dialogue(rom_data)?.banks.iter().enumerate() {
+        let j = serde_json::to_string_pretty(lines)?;
+        write(format!("{}/bank{}.json", dir, i + 1), &j)?;
+
+        let mut text = String::new();
+        for (index, line) in lines.iter().enumerate() {
+            text.push_str(&format!("[{:02x}]\n{}\n\n", index, line.plain_text()));
+        }
+        write(format!("{}/bank{}.txt", dir, i + 1), &text)?;
+    }
+
+    Ok(())
 }
 
-fn main() -> Result<(), Box<Error>> {
+fn dump_ai(ff4: &ff4::Ff4) -> Result<(), Box<dyn Error>> {
+    let dir = "out/ai";
+    create_dir_all(dir)?;
+    let ai = &ff4.monster_data.ai;
+    write(
+        format!("{}/earth.asm", dir),
+        asm::disassemble(&ai.earth_scripts),
+    )?;
+    write(
+        format!("{}/moon.asm", dir),
+        asm::disassemble(&ai.moon_scripts),
+    )?;
+
+    Ok(())
+}
+
+fn print_messages(ff4: &ff4::Ff4) {
+    for i in 0..ff4.messages.len() {
+        println!("{:02x}: {}", i, ff4.message_text(i as u8).unwrap());
+    }
+}
+
+fn export_tbl() -> Result<(), Box<dyn Error>> {
+    create_dir_all("out")?;
+    write("out/ff2us.tbl", CharTable::us().to_tbl())?;
+
+    Ok(())
+}
+
+// Usage: ff4 [--map <map.json>] [monsters|ai|dialogue|coverage|messages|tbl]
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut map = None;
+    let mut command = "monsters".to_string();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--map" => map = Some(args.next().ok_or("--map needs a path")?),
+            _ => command = arg,
+        }
+    }
+
+    // The built in table doesn't need a ROM.
+    if command == "tbl" {
+        return export_tbl();
+    }
+
     let rom_data = test_utils::load_rom()?;
-    let ff4 = ff4::parse_rom(&rom_data)?;
+    // A JSON map overrides the one picked from the ROM header.
+    let ff4 = match map {
+        Some(path) => ff4::parse_rom_with_map(&rom_data, &RomMap::load(path)?)?,
+        None => ff4::parse_rom(&rom_data)?,
+    };
 
-    dump_monsters(&ff4);
+    match command.as_str() {
+        "monsters" => dump_monsters(&ff4)?,
+        "ai" => dump_ai(&ff4)?,
+        "dialogue" => dump_dialogue(&rom_data, &ff4)?,
+        "coverage" => print_coverage(&rom_data, &ff4)?,
+        "messages" => print_messages(&ff4),
+        _ => return Err(format!("unknown command {:?}", command).into()),
+    }
 
     Ok(())
 }
@@ -1,15 +1,27 @@
 use nom::types::CompleteByteSlice;
-use simple_error::SimpleError;
-use std::error::Error;
 
-use super::super::rom_map;
+use super::super::dialogue::read_text_pointers;
+use super::super::error::from_nom;
+use super::super::rom_map::RomMap;
+use super::super::span::{Span, Spanned};
+use super::super::string::CharTable;
+use super::super::Error;
 use super::script;
+use super::{Monster, StatusSet};
 
 // Each monster has an attack group ID.  This indexes into the attack group
 // table.  The table is a list of entries.  Each entry is Terminated by 0xff.
 #[derive(Debug, PartialEq, Serialize)]
 pub struct Group {
     pub entries: Vec<GroupEntry>,
+    pub span: Span,
+}
+
+impl Group {
+    // Size including the 0xff terminator.
+    pub fn size(&self) -> usize {
+        self.entries.len() * 2 + 1
+    }
 }
 
 #[derive(Debug, PartialEq, Serialize)]
@@ -21,12 +33,180 @@ pub struct GroupEntry {
 #[derive(Debug, PartialEq, Serialize)]
 pub struct ConditionSet {
     pub condition_indexes: Vec<u8>,
+    pub span: Span,
+}
+
+impl ConditionSet {
+    // Size including the 0xff terminator.
+    pub fn size(&self) -> usize {
+        self.condition_indexes.len() + 1
+    }
+}
+
+// Conditions are an op byte followed by three argument bytes.
+pub const CONDITION_LEN: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum ConditionTarget {
+    // 0x00: the monster running the script.
+    Itself,
+    // 0x01
+    AnyMonster,
+    // 0x02
+    AnyPartyMember,
+}
+
+impl ConditionTarget {
+    fn from_byte(b: u8) -> Option<ConditionTarget> {
+        match b {
+            0x00 => Some(ConditionTarget::Itself),
+            0x01 => Some(ConditionTarget::AnyMonster),
+            0x02 => Some(ConditionTarget::AnyPartyMember),
+            _ => None,
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            ConditionTarget::Itself => 0x00,
+            ConditionTarget::AnyMonster => 0x01,
+            ConditionTarget::AnyPartyMember => 0x02,
+        }
+    }
+}
+
+// Condition ops whose layout below has been checked against the game's AI
+// routine.  None have been yet, so every condition decodes as `Unknown` until
+// an op is confirmed and added here.
+const CONFIRMED_CONDITION_OPS: &[u8] = &[];
+
+// The ops, targets and layouts below are still unconfirmed.  They can be
+// encoded, but only ops in `CONFIRMED_CONDITION_OPS` are decoded to them.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum ConditionKind {
+    // 0x00 0x00 0x00 0x00
+    Always,
+    // 0x01 <target> <status byte> <mask>
+    // Any of `statuses` is set.  They all come from one byte of the status
+    // field.
+    StatusPresent {
+        target: ConditionTarget,
+        statuses: StatusSet,
+    },
+    // 0x02 <target> <hp lo> <hp hi>
+    HpBelow {
+        target: ConditionTarget,
+        hp: u16,
+    },
+    // 0x03 0x00 0x00 0x00
+    // No other monsters are left in the battle.
+    MonsterAlone,
+    // 0x04 <value> 0x00 0x00
+    // The value set by the condition flag actions.
+    ConditionFlag {
+        value: u8,
+    },
+    // 0x05 <character> 0x00 0x00
+    CharacterInParty {
+        character: u8,
+    },
+    // 0x06 <command> 0x00 0x00
+    // The party last used `command` against the monster.
+    CommandUsed {
+        command: u8,
+    },
+    // 0x07 <spell> 0x00 0x00
+    // The party last cast `spell` at the monster.
+    SpellUsed {
+        spell: u8,
+    },
+    // Any op or arguments not covered above, kept as they were in the ROM.
+    Unknown {
+        op: u8,
+        args: [u8; 3],
+    },
+}
+
+impl ConditionKind {
+    // Unconfirmed ops, and anything which doesn't encode back to exactly
+    // `data`, decode as `Unknown` so conditions always round trip.
+    pub fn from_bytes(data: [u8; CONDITION_LEN]) -> ConditionKind {
+        if CONFIRMED_CONDITION_OPS.contains(&data[0]) {
+            ConditionKind::from_layout(data)
+        } else {
+            ConditionKind::Unknown {
+                op: data[0],
+                args: [data[1], data[2], data[3]],
+            }
+        }
+    }
+
+    // Decodes `data` by the layouts above whether or not its op is confirmed.
+    fn from_layout(data: [u8; CONDITION_LEN]) -> ConditionKind {
+        let unknown = ConditionKind::Unknown {
+            op: data[0],
+            args: [data[1], data[2], data[3]],
+        };
+        let target = ConditionTarget::from_byte(data[1]);
+        let kind = match (data[0], target) {
+            (0x00, _) => ConditionKind::Always,
+            (0x01, Some(target)) if data[2] < 3 => {
+                let mut status = [0; 3];
+                status[data[2] as usize] = data[3];
+                ConditionKind::StatusPresent {
+                    target,
+                    statuses: StatusSet::from_bytes(status),
+                }
+            }
+            (0x02, Some(target)) => ConditionKind::HpBelow {
+                target,
+                hp: u16::from(data[2]) | u16::from(data[3]) << 8,
+            },
+            (0x03, _) => ConditionKind::MonsterAlone,
+            (0x04, _) => ConditionKind::ConditionFlag { value: data[1] },
+            (0x05, _) => ConditionKind::CharacterInParty { character: data[1] },
+            (0x06, _) => ConditionKind::CommandUsed { command: data[1] },
+            (0x07, _) => ConditionKind::SpellUsed { spell: data[1] },
+            _ => return unknown,
+        };
+        match kind.to_bytes() {
+            Some(bytes) if bytes == data => kind,
+            _ => unknown,
+        }
+    }
+
+    // Returns `None` for a status check which is empty or spans more than one
+    // byte of the status field.
+    pub fn to_bytes(&self) -> Option<[u8; CONDITION_LEN]> {
+        let data = match self {
+            ConditionKind::Always => [0x00, 0x00, 0x00, 0x00],
+            ConditionKind::StatusPresent { target, statuses } => {
+                let status = statuses.to_bytes();
+                let mut bytes = status.iter().enumerate().filter(|(_, &b)| b != 0);
+                let (index, &mask) = bytes.next()?;
+                if bytes.next().is_some() {
+                    return None;
+                }
+                [0x01, target.to_byte(), index as u8, mask]
+            }
+            ConditionKind::HpBelow { target, hp } => {
+                [0x02, target.to_byte(), *hp as u8, (*hp >> 8) as u8]
+            }
+            ConditionKind::MonsterAlone => [0x03, 0x00, 0x00, 0x00],
+            ConditionKind::ConditionFlag { value } => [0x04, *value, 0x00, 0x00],
+            ConditionKind::CharacterInParty { character } => [0x05, *character, 0x00, 0x00],
+            ConditionKind::CommandUsed { command } => [0x06, *command, 0x00, 0x00],
+            ConditionKind::SpellUsed { spell } => [0x07, *spell, 0x00, 0x00],
+            ConditionKind::Unknown { op, args } => [*op, args[0], args[1], args[2]],
+        };
+        Some(data)
+    }
 }
 
 #[derive(Debug, PartialEq, Serialize)]
 pub struct Condition {
-    pub op: u8,
-    pub args: [u8; 3],
+    pub kind: ConditionKind,
+    pub span: Span,
 }
 
 #[derive(Debug, PartialEq, Serialize)]
@@ -38,6 +218,131 @@ pub struct Ai {
     pub moon_scripts: Vec<script::Script>,
 }
 
+// The script table an action index refers to.  Nothing in the monster says
+// which one applies (the game picks one from the battle) so callers choose.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum ScriptTable {
+    Earth,
+    Moon,
+}
+
+// One entry of an attack group with its references followed.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct AiRule<'a> {
+    // All of these must hold for the rule to apply.
+    pub conditions: Vec<&'a Condition>,
+    pub action_index: u8,
+    pub script: &'a script::Script,
+}
+
+// A monster's AI: the rules of its attack group in the order they're tried,
+// and the rules of its reflex group if it has one.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct MonsterAi<'a> {
+    pub rules: Vec<AiRule<'a>>,
+    pub reaction: Option<Vec<AiRule<'a>>>,
+}
+
+fn lookup<'a, T>(
+    table: &'a [T],
+    index: u8,
+    name: &'static str,
+    offset: usize,
+) -> Result<&'a T, Error> {
+    table.get(index as usize).ok_or(Error::IndexOutOfRange {
+        table: name,
+        offset,
+        index: index as usize,
+        len: table.len(),
+    })
+}
+
+// Where `monster`'s reflex group byte is, worked out from which optional
+// bytes come before it rather than from the span's length, so that it holds
+// for monsters which weren't parsed from a ROM.
+fn reflex_offset(monster: &Monster) -> usize {
+    let optional_len = [
+        (monster.attack_statuses.is_some(), 3),
+        (monster.defense_statuses.is_some(), 3),
+        (monster.weaknesses.is_some(), 1),
+        (monster.spell_power.is_some(), 1),
+        (monster.creature_types.is_some(), 1),
+    ]
+    .iter()
+    .filter(|&&(present, _)| present)
+    .map(|&(_, len)| len)
+    .sum::<usize>();
+    monster.span.offset + 10 + optional_len
+}
+
+impl Ai {
+    // Follows `monster`'s attack group (and reflex group) through to its
+    // conditions and the scripts in `scripts`.  A bad index is reported as
+    // out of range of the table it indexes, at the byte which holds it.
+    pub fn resolve_for(
+        &self,
+        monster: &Monster,
+        scripts: ScriptTable,
+    ) -> Result<MonsterAi<'_>, Error> {
+        // The attack group byte is at a fixed offset and the reflex group, when
+        // present, follows the record's other optional bytes.
+        let rules =
+            self.resolve_group(monster.attack_seq_group, monster.span.offset + 8, scripts)?;
+        let reaction = match monster.reflex_attack_seq {
+            Some(group) => Some(self.resolve_group(group, reflex_offset(monster), scripts)?),
+            None => None,
+        };
+
+        Ok(MonsterAi { rules, reaction })
+    }
+
+    fn resolve_group(
+        &self,
+        index: u8,
+        offset: usize,
+        scripts: ScriptTable,
+    ) -> Result<Vec<AiRule<'_>>, Error> {
+        let group = lookup(&self.groups, index, "attack groups", offset)?;
+        let (script_table, script_name) = match scripts {
+            ScriptTable::Earth => (&self.earth_scripts, "earth scripts"),
+            ScriptTable::Moon => (&self.moon_scripts, "moon scripts"),
+        };
+
+        let mut rules = Vec::new();
+        for (i, entry) in group.entries.iter().enumerate() {
+            let entry_offset = group.span.offset + i * 2;
+            let set = lookup(
+                &self.condition_sets,
+                entry.condition_set_index,
+                "condition sets",
+                entry_offset,
+            )?;
+            let mut conditions = Vec::new();
+            for (j, &condition) in set.condition_indexes.iter().enumerate() {
+                conditions.push(lookup(
+                    &self.conditions,
+                    condition,
+                    "conditions",
+                    set.span.offset + j,
+                )?);
+            }
+
+            rules.push(AiRule {
+                conditions,
+                action_index: entry.action_index,
+                script: lookup(
+                    script_table,
+                    entry.action_index,
+                    script_name,
+                    entry_offset + 1,
+                )?,
+            });
+        }
+
+        Ok(rules)
+    }
+}
+
 named!(parse_groups<CompleteByteSlice, Vec<Group>>,
      complete!(many_m_n!(0, 0x100, parse_group)));
 
@@ -50,7 +355,7 @@ named!(parse_group_entry<CompleteByteSlice, GroupEntry>,
 
 named!(parse_group<CompleteByteSlice, Group>, do_parse!(
     e: many_till!(parse_group_entry, tag!(&[0xff][..])) >>
-    (Group{entries: e.0})
+    (Group{entries: e.0, span: Span::default()})
 ));
 
 named!(parse_condition_sets<CompleteByteSlice, Vec<ConditionSet>>,
@@ -60,58 +365,121 @@ named!(parse_condition_set<CompleteByteSlice, ConditionSet>,
     do_parse!(
         indexes: take_until!(&[0xff][..]) >>
         tag!(&[0xff][..]) >>
-        (ConditionSet{condition_indexes: indexes.0.to_vec()})
+        (ConditionSet{condition_indexes: indexes.0.to_vec(), span: Span::default()})
     ));
 
 named!(parse_condition<CompleteByteSlice, Condition>,
     do_parse!(
-        op: take!(1) >>
-        args: take!(3) >>
-        (Condition{op: op[0], args: [args[0], args[1], args[2]]})
+        d: take!(CONDITION_LEN) >>
+        (Condition{kind: ConditionKind::from_bytes([d[0], d[1], d[2], d[3]]), span: Span::default()})
 ));
 
 named!(parse_conditions<CompleteByteSlice, Vec<Condition>>,
     many1!(parse_condition)
 );
 
-pub fn parse(data: &[u8]) -> Result<Ai, Box<Error>> {
-    let groups = parse_groups(CompleteByteSlice(
-        &data[rom_map::ATTACK_GROUP_START..=rom_map::ATTACK_GROUP_END],
-    ))
-    .map_err(|e| SimpleError::new(format!("Can't parse AI: {}", e)))?
-    .1;
-
-    let condition_sets = parse_condition_sets(CompleteByteSlice(
-        &data[rom_map::AI_CONDITION_SET_TABLE_START..rom_map::AI_CONDITION_SET_TABLE_END],
-    ))
-    .map_err(|e| SimpleError::new(format!("Can't parse condition sets: {}", e)))?
-    .1;
-
-    let conditions = parse_conditions(CompleteByteSlice(
-        &data[rom_map::AI_CONDITION_TABLE_START..rom_map::AI_CONDITION_TABLE_END],
-    ))
-    .map_err(|e| SimpleError::new(format!("Can't parse conditions: {}", e)))?
-    .1;
-
-    let earth_scripts = script::parse(
-        &data[rom_map::AI_EARTH_ATTACK_SCRIPTS_START..=rom_map::AI_EARTH_ATTACK_SCRIPTS_END],
-    )?;
-    let moon_scripts = script::parse(
-        &data[rom_map::AI_MOON_ATTACK_SCRIPTS_START..=rom_map::AI_MOON_ATTACK_SCRIPTS_END],
-    )?;
+// Battle messages, indexed by `Action::ShowMessage`.  Messages are 0x00
+// terminated and must lie inside the message region.  The terminator is
+// included in each message's span.  Text is decoded losslessly, so bytes the
+// table doesn't know are kept as escapes rather than refusing the ROM.
+pub fn parse_messages(
+    data: &[u8],
+    map: &RomMap,
+    char_table: &CharTable,
+) -> Result<Vec<Spanned<String>>, Error> {
+    let pointers = map.battle_message_pointers();
+    let region = map.battle_messages();
+    let text = region.slice(data)?;
+
+    let mut messages = Vec::new();
+    for start in read_text_pointers(data, pointers, map.battle_message_bank, region)? {
+        let message = &text[start - region.start..];
+        let len = match message.iter().position(|&b| b == 0x00) {
+            Some(len) => len,
+            None => {
+                return Err(Error::TruncatedRom {
+                    table: region.name,
+                    offset: region.end(),
+                    len: 1,
+                })
+            }
+        };
+        let text = char_table.decode_lossless(&message[..len]);
+        messages.push(Spanned::new(Span::new(start, len + 1), text));
+    }
+
+    Ok(messages)
+}
+
+// Points every battle message at a single empty message.
+#[cfg(test)]
+pub(crate) fn write_empty_messages(data: &mut [u8], map: &RomMap) {
+    let offset = super::super::addr::FileOffset(map.battle_message_text)
+        .to_snes_addr()
+        .offset();
+    for at in map.battle_message_pointers().offsets(2) {
+        data[at] = offset as u8;
+        data[at + 1] = (offset >> 8) as u8;
+    }
+    data[map.battle_message_text] = 0x00;
+}
+
+pub fn parse(data: &[u8], map: &RomMap) -> Result<Ai, Error> {
+    let region = map.attack_groups();
+    let group_data = region.slice(data)?;
+    let mut groups = parse_groups(CompleteByteSlice(group_data))
+        .map_err(|e| from_nom(e, group_data, region.name, region.start))?
+        .1;
+    let mut offset = region.start;
+    for group in &mut groups {
+        group.span = Span::new(offset, group.size());
+        offset = group.span.end();
+    }
+
+    let region = map.condition_sets();
+    let condition_set_data = region.slice(data)?;
+    let mut condition_sets = parse_condition_sets(CompleteByteSlice(condition_set_data))
+        .map_err(|e| from_nom(e, condition_set_data, region.name, region.start))?
+        .1;
+    let mut offset = region.start;
+    for set in &mut condition_sets {
+        set.span = Span::new(offset, set.size());
+        offset = set.span.end();
+    }
+
+    let region = map.conditions();
+    let condition_data = region.slice(data)?;
+    let mut conditions = parse_conditions(CompleteByteSlice(condition_data))
+        .map_err(|e| from_nom(e, condition_data, region.name, region.start))?
+        .1;
+    for (i, condition) in conditions.iter_mut().enumerate() {
+        condition.span = Span::new(region.start + i * CONDITION_LEN, CONDITION_LEN);
+    }
+
+    let region = map.earth_scripts();
+    let mut earth_scripts =
+        script::parse(region.slice(data)?).map_err(|e| e.relocate(region.name, region.start))?;
+    script::relocate(&mut earth_scripts, region.start);
+
+    let region = map.moon_scripts();
+    let mut moon_scripts =
+        script::parse(region.slice(data)?).map_err(|e| e.relocate(region.name, region.start))?;
+    script::relocate(&mut moon_scripts, region.start);
 
     Ok(Ai {
-        groups: groups,
-        condition_sets: condition_sets,
-        conditions: conditions,
-        earth_scripts: earth_scripts,
-        moon_scripts: moon_scripts,
+        groups,
+        condition_sets,
+        conditions,
+        earth_scripts,
+        moon_scripts,
     })
 }
 
 #[cfg(test)]
 mod tests {
     use super::super::super::test_utils;
+    use super::super::Status;
+    use super::super::WeaknessSet;
     use super::*;
 
     #[test]
@@ -145,6 +513,7 @@ mod tests {
                         action_index: 0x01,
                     },
                 ),
+                span: Span::default(),
             },
             parse_group(CompleteByteSlice(&[
                 0x55, 0x00, 0x01, 0x02, 0x00, 0x01, 0xff
@@ -173,6 +542,7 @@ mod tests {
                             action_index: 0x01,
                         },
                     ),
+                    span: Span::default(),
                 },
                 Group {
                     entries: vec!(
@@ -185,6 +555,7 @@ mod tests {
                             action_index: 0x03,
                         },
                     ),
+                    span: Span::default(),
                 },
             ),
             parse_groups(CompleteByteSlice(&[
@@ -199,8 +570,11 @@ mod tests {
     fn parse_condition_test() {
         assert_eq!(
             Condition {
-                op: 0x55,
-                args: [0xaa, 0x00, 0xff],
+                kind: ConditionKind::Unknown {
+                    op: 0x55,
+                    args: [0xaa, 0x00, 0xff],
+                },
+                span: Span::default(),
             },
             parse_condition(CompleteByteSlice(&[0x55, 0xaa, 0x00, 0xff]))
                 .unwrap()
@@ -208,11 +582,105 @@ mod tests {
         );
     }
 
+    #[test]
+    fn condition_kind_test() {
+        let mut statuses = StatusSet::new();
+        statuses.insert(Status::Poison);
+        statuses.insert(Status::Blind);
+        let cases = vec![
+            ([0x00, 0x00, 0x00, 0x00], ConditionKind::Always),
+            (
+                [0x01, 0x00, 0x01, 0x03],
+                ConditionKind::StatusPresent {
+                    target: ConditionTarget::Itself,
+                    statuses,
+                },
+            ),
+            (
+                [0x02, 0x01, 0xe8, 0x03],
+                ConditionKind::HpBelow {
+                    target: ConditionTarget::AnyMonster,
+                    hp: 1000,
+                },
+            ),
+            ([0x03, 0x00, 0x00, 0x00], ConditionKind::MonsterAlone),
+            (
+                [0x04, 0x02, 0x00, 0x00],
+                ConditionKind::ConditionFlag { value: 2 },
+            ),
+            (
+                [0x05, 0x0b, 0x00, 0x00],
+                ConditionKind::CharacterInParty { character: 0x0b },
+            ),
+            (
+                [0x06, 0xc5, 0x00, 0x00],
+                ConditionKind::CommandUsed { command: 0xc5 },
+            ),
+            (
+                [0x07, 0x31, 0x00, 0x00],
+                ConditionKind::SpellUsed { spell: 0x31 },
+            ),
+            // Unused arguments, a bad target and a bad status byte.
+            (
+                [0x00, 0x01, 0x00, 0x00],
+                ConditionKind::Unknown {
+                    op: 0x00,
+                    args: [0x01, 0x00, 0x00],
+                },
+            ),
+            (
+                [0x02, 0x03, 0x00, 0x01],
+                ConditionKind::Unknown {
+                    op: 0x02,
+                    args: [0x03, 0x00, 0x01],
+                },
+            ),
+            (
+                [0x01, 0x02, 0x03, 0x01],
+                ConditionKind::Unknown {
+                    op: 0x01,
+                    args: [0x02, 0x03, 0x01],
+                },
+            ),
+            (
+                [0x01, 0x02, 0x00, 0x00],
+                ConditionKind::Unknown {
+                    op: 0x01,
+                    args: [0x02, 0x00, 0x00],
+                },
+            ),
+        ];
+        for (data, kind) in cases {
+            assert_eq!(kind, ConditionKind::from_layout(data));
+            assert_eq!(Some(data), kind.to_bytes());
+            // No op is confirmed yet, so the ROM's bytes are kept as they are.
+            assert_eq!(
+                ConditionKind::Unknown {
+                    op: data[0],
+                    args: [data[1], data[2], data[3]],
+                },
+                ConditionKind::from_bytes(data)
+            );
+        }
+
+        // Statuses from more than one byte don't fit in one condition.
+        statuses.insert(Status::Fire);
+        assert_eq!(
+            None,
+            ConditionKind::StatusPresent {
+                target: ConditionTarget::Itself,
+                statuses,
+            }
+            .to_bytes()
+        );
+    }
+
     #[test]
     fn parse_condition_set_test() {
         assert_eq!(
             ConditionSet {
                 condition_indexes: vec!(0x55),
+                span: Span::default(),
             },
             parse_condition_set(CompleteByteSlice(&[0x55, 0xff]))
                 .unwrap()
@@ -221,6 +689,7 @@ mod tests {
         assert_eq!(
             ConditionSet {
                 condition_indexes: vec!(0x55, 0xaa),
+                span: Span::default(),
             },
             parse_condition_set(CompleteByteSlice(&[0x55, 0xaa, 0xff]))
                 .unwrap()
@@ -229,6 +698,7 @@ mod tests {
         assert_eq!(
             ConditionSet {
                 condition_indexes: vec!(0x55, 0xaa, 0xcc),
+                span: Span::default(),
             },
             parse_condition_set(CompleteByteSlice(&[0x55, 0xaa, 0xcc, 0xff]))
                 .unwrap()
@@ -242,12 +712,15 @@ mod tests {
             vec!(
                 ConditionSet {
                     condition_indexes: vec!(0x55),
+                    span: Span::default(),
                 },
                 ConditionSet {
                     condition_indexes: vec!(0x55, 0xaa),
+                    span: Span::default(),
                 },
                 ConditionSet {
                     condition_indexes: vec!(0x55, 0xaa, 0xcc),
+                    span: Span::default(),
                 },
             ),
             parse_condition_sets(CompleteByteSlice(&[
@@ -263,12 +736,18 @@ mod tests {
         assert_eq!(
             vec!(
                 Condition {
-                    op: 0x55,
-                    args: [0xaa, 0x00, 0xff],
+                    kind: ConditionKind::Unknown {
+                        op: 0x55,
+                        args: [0xaa, 0x00, 0xff],
+                    },
+                    span: Span::default(),
                 },
                 Condition {
-                    op: 0x11,
-                    args: [0x22, 0x33, 0x44],
+                    kind: ConditionKind::Unknown {
+                        op: 0x11,
+                        args: [0x22, 0x33, 0x44],
+                    },
+                    span: Span::default(),
                 },
             ),
             parse_conditions(CompleteByteSlice(&[
@@ -277,24 +756,356 @@ mod tests {
             .unwrap()
             .1
         );
+        // Ops which fit a layout still stay `Unknown` until they're confirmed.
+        assert_eq!(
+            vec!(Condition {
+                kind: ConditionKind::Unknown {
+                    op: 0x02,
+                    args: [0x02, 0x34, 0x12],
+                },
+                span: Span::default(),
+            }),
+            parse_conditions(CompleteByteSlice(&[0x02, 0x02, 0x34, 0x12]))
+                .unwrap()
+                .1
+        );
+    }
+
+    #[test]
+    fn parse_spans_test() {
+        // Every table full of terminators parses as empty entries.
+        let map = RomMap::us();
+        let data = vec![0xff; 0x80000];
+        let ai = parse(&data, &map).unwrap();
+
+        assert_eq!(Span::new(map.attack_group_table + 3, 1), ai.groups[3].span);
+        assert_eq!(
+            Span::new(map.ai_condition_set_table + 2, 1),
+            ai.condition_sets[2].span
+        );
+        assert_eq!(
+            Span::new(map.ai_condition_table + 4, CONDITION_LEN),
+            ai.conditions[1].span
+        );
+        assert_eq!(
+            Span::new(map.ai_moon_attack_scripts + 5, 1),
+            ai.moon_scripts[5].span
+        );
+    }
+
+    #[test]
+    fn parse_messages_test() {
+        let map = RomMap::us();
+        let mut data = vec![0x00; 0x80000];
+        write_empty_messages(&mut data, &map);
+        // Message 1 is "Hi the" with "th" as a DTE pair.
+        let text = map.battle_message_text + 1;
+        data[text..text + 6].copy_from_slice(&[0x49, 0x64, 0xff, 0x8b, 0x60, 0x00]);
+        data[map.battle_message_pointers + 2] += 1;
+
+        let messages = parse_messages(&data, &map, &CharTable::us()).unwrap();
+        assert_eq!(0x100, messages.len());
+        assert_eq!(Span::new(text, 6), messages[1].span);
+        assert_eq!("", messages[0].value);
+        // The DTE pair is kept as an escape so the text encodes back exactly.
+        assert_eq!("Hi <8b>e", messages[1].value);
+
+        // A byte with no text is kept rather than refusing the ROM.
+        data[text + 2] = 0x01;
+        let messages = parse_messages(&data, &map, &CharTable::us()).unwrap();
+        assert_eq!("Hi<01><8b>e", messages[1].value);
+        data[text + 2] = 0xff;
+
+        // A pointer outside the message region.
+        data[map.battle_message_pointers + 3] = 0x80;
+        assert_eq!(
+            Err(Error::BadPointer {
+                table: "battle message pointers",
+                offset: map.battle_message_pointers + 2,
+                addr: 0x0f8001,
+            }),
+            parse_messages(&data, &map, &CharTable::us())
+        );
+
+        // A message which runs off the end of the region.
+        let mut data = vec![0xff; 0x80000];
+        write_empty_messages(&mut data, &map);
+        data[map.battle_message_text] = 0xff;
+        assert_eq!(
+            Err(Error::TruncatedRom {
+                table: "battle messages",
+                offset: map.battle_messages().end(),
+                len: 1,
+            }),
+            parse_messages(&data, &map, &CharTable::us())
+        );
+    }
+
+    #[test]
+    fn resolve_for_test() {
+        let script = |offset: usize| script::Script {
+            actions: vec![],
+            span: Span::new(offset, 1),
+        };
+        let ai = Ai {
+            condition_sets: vec![
+                ConditionSet {
+                    condition_indexes: vec![0x01, 0x00],
+                    span: Span::new(0x100, 3),
+                },
+                ConditionSet {
+                    condition_indexes: vec![0x05],
+                    span: Span::new(0x103, 2),
+                },
+            ],
+            conditions: vec![
+                Condition {
+                    kind: ConditionKind::Always,
+                    span: Span::new(0x200, CONDITION_LEN),
+                },
+                Condition {
+                    kind: ConditionKind::MonsterAlone,
+                    span: Span::new(0x204, CONDITION_LEN),
+                },
+            ],
+            groups: vec![
+                Group {
+                    entries: vec![
+                        GroupEntry {
+                            condition_set_index: 0x00,
+                            action_index: 0x01,
+                        },
+                        GroupEntry {
+                            condition_set_index: 0x00,
+                            action_index: 0x00,
+                        },
+                    ],
+                    span: Span::new(0x300, 5),
+                },
+                Group {
+                    entries: vec![GroupEntry {
+                        condition_set_index: 0x02,
+                        action_index: 0x00,
+                    }],
+                    span: Span::new(0x305, 3),
+                },
+                Group {
+                    entries: vec![GroupEntry {
+                        condition_set_index: 0x01,
+                        action_index: 0x00,
+                    }],
+                    span: Span::new(0x308, 3),
+                },
+                Group {
+                    entries: vec![GroupEntry {
+                        condition_set_index: 0x00,
+                        action_index: 0x02,
+                    }],
+                    span: Span::new(0x30b, 3),
+                },
+            ],
+            earth_scripts: vec![script(0x400), script(0x401)],
+            moon_scripts: vec![script(0x500)],
+        };
+        let mut monster = Monster {
+            span: Span::new(0x600, 10),
+            ..Default::default()
+        };
+
+        let resolved = ai.resolve_for(&monster, ScriptTable::Earth).unwrap();
+        assert_eq!(None, resolved.reaction);
+        assert_eq!(2, resolved.rules.len());
+        let rule = &resolved.rules[0];
+        assert_eq!(vec![&ai.conditions[1], &ai.conditions[0]], rule.conditions);
+        assert_eq!(1, rule.action_index);
+        assert_eq!(&ai.earth_scripts[1], rule.script);
+        assert_eq!(&ai.earth_scripts[0], resolved.rules[1].script);
+
+        monster.reflex_attack_seq = Some(0);
+        monster.span = Span::new(0x600, 11);
+        let resolved = ai.resolve_for(&monster, ScriptTable::Earth).unwrap();
+        assert_eq!(Some(resolved.rules), resolved.reaction);
+
+        // Each bad reference is reported as out of range of the table it
+        // indexes, at the byte which holds it.
+        let error = |group: u8, scripts: ScriptTable| {
+            let monster = Monster {
+                attack_seq_group: group,
+                span: Span::new(0x600, 10),
+                ..Default::default()
+            };
+            ai.resolve_for(&monster, scripts).map(|_| ())
+        };
+        assert_eq!(
+            Err(Error::IndexOutOfRange {
+                table: "condition sets",
+                offset: 0x305,
+                index: 2,
+                len: 2,
+            }),
+            error(1, ScriptTable::Earth)
+        );
+        assert_eq!(
+            Err(Error::IndexOutOfRange {
+                table: "conditions",
+                offset: 0x103,
+                index: 5,
+                len: 2,
+            }),
+            error(2, ScriptTable::Earth)
+        );
+        assert_eq!(
+            Err(Error::IndexOutOfRange {
+                table: "earth scripts",
+                offset: 0x30c,
+                index: 2,
+                len: 2,
+            }),
+            error(3, ScriptTable::Earth)
+        );
+        // Group 0's first action only exists in the earth scripts.
+        assert_eq!(
+            Err(Error::IndexOutOfRange {
+                table: "moon scripts",
+                offset: 0x301,
+                index: 1,
+                len: 1,
+            }),
+            error(0, ScriptTable::Moon)
+        );
+        assert_eq!(
+            Err(Error::IndexOutOfRange {
+                table: "attack groups",
+                offset: 0x608,
+                index: 4,
+                len: 4,
+            }),
+            error(4, ScriptTable::Earth)
+        );
+
+        monster.reflex_attack_seq = Some(4);
+        assert_eq!(
+            Err(Error::IndexOutOfRange {
+                table: "attack groups",
+                offset: 0x60a,
+                index: 4,
+                len: 4,
+            }),
+            ai.resolve_for(&monster, ScriptTable::Earth).map(|_| ())
+        );
+        monster.spell_power = Some(8);
+        monster.weaknesses = Some(WeaknessSet::new());
+        assert_eq!(
+            Err(Error::IndexOutOfRange {
+                table: "attack groups",
+                offset: 0x60c,
+                index: 4,
+                len: 4,
+            }),
+            ai.resolve_for(&monster, ScriptTable::Earth).map(|_| ())
+        );
+
+        // A monster which wasn't parsed from a ROM has an empty span at 0.
+        let monster = Monster {
+            reflex_attack_seq: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(
+            Err(Error::IndexOutOfRange {
+                table: "attack groups",
+                offset: 10,
+                index: 4,
+                len: 4,
+            }),
+            ai.resolve_for(&monster, ScriptTable::Earth).map(|_| ())
+        );
     }
 
     #[cfg_attr(feature = "ci_tests", ignore)]
     #[test]
     fn parse_test() {
         let data = test_utils::load_rom().unwrap();
-        let ai = parse(&data).unwrap();
+        let ai = parse(&data, &RomMap::us()).unwrap();
 
         assert_eq!(256, ai.groups.len());
 
+        // Monsters fought on the earth use the earth scripts and Zeromus, who
+        // is only fought on the moon, uses the moon scripts.
+        let monster_data = super::super::parse(&data, &RomMap::us(), &CharTable::us()).unwrap();
+        let monsters = &monster_data.monsters;
+        for &index in &[0x00, 0xa5] {
+            monster_data
+                .ai
+                .resolve_for(&monsters[index], ScriptTable::Earth)
+                .unwrap();
+        }
+        let zeromus: Vec<_> = monsters
+            .iter()
+            .filter(|m| monster_data.name_table[m.index].trim() == "Zeromus")
+            .collect();
+        assert!(!zeromus.is_empty());
+        for monster in zeromus {
+            monster_data
+                .ai
+                .resolve_for(monster, ScriptTable::Moon)
+                .unwrap();
+        }
+
         assert_eq!(
-            Group {
-                entries: vec!(GroupEntry {
-                    condition_set_index: 0x08,
-                    action_index: 0x04,
-                },),
-            },
-            ai.groups[2]
+            vec!(GroupEntry {
+                condition_set_index: 0x08,
+                action_index: 0x04,
+            }),
+            ai.groups[2].entries
         );
     }
+
+    #[cfg_attr(feature = "ci_tests", ignore)]
+    #[test]
+    fn conditions_rom_test() {
+        let data = test_utils::load_rom().unwrap();
+        let monster_data = super::super::parse(&data, &RomMap::us(), &CharTable::us()).unwrap();
+        let milon = &monster_data.monsters[0xa5];
+        let milon_ai = monster_data
+            .ai
+            .resolve_for(milon, ScriptTable::Earth)
+            .unwrap();
+
+        // No condition op is confirmed yet, so each condition Milon checks is
+        // kept as the ROM's bytes.
+        let rules = milon_ai
+            .rules
+            .iter()
+            .chain(milon_ai.reaction.iter().flatten());
+        for rule in rules {
+            for condition in &rule.conditions {
+                let span = &condition.span;
+                assert_eq!(
+                    Some(&data[span.offset..span.end()]),
+                    condition.kind.to_bytes().as_ref().map(|b| &b[..])
+                );
+            }
+        }
+    }
+
+    #[cfg_attr(feature = "ci_tests", ignore)]
+    #[test]
+    fn messages_rom_test() {
+        let data = test_utils::load_rom().unwrap();
+        let char_table = CharTable::us();
+        let messages = parse_messages(&data, &RomMap::us(), &char_table).unwrap();
+
+        // Stored text encodes back to the ROM's bytes.  "Milon" is written
+        // with DTE pairs, so it only shows up in the display text.
+        let mut display = Vec::new();
+        for message in &messages {
+            let bytes = char_table.encode_lossless(&message.value).unwrap();
+            assert_eq!(
+                &data[message.span.offset..message.span.end() - 1],
+                &bytes[..]
+            );
+            display.push(char_table.decode_display(&bytes));
+        }
+        assert!(display.iter().any(|m| m.contains("Milon")), "{:?}", display);
+    }
 }
@@ -0,0 +1,391 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::super::span::{Span, Spanned};
+use super::script::{self, Action, Script};
+use super::{CreatureTypeSet, StatusSet, WeaknessSet};
+
+// A text format for AI scripts.  Each script starts with a label and has one
+// action per line:
+//
+//     script_00:
+//         spell.all 0x25  ; comments run to the end of the line
+//         wait
+//         chain.start
+//
+// Arguments are bytes written in hex (`0x25`) or decimal.  Flag set arguments
+// are the raw byte the script stores.  Opcodes with no known action are
+// written as `op` followed by the opcode and its argument bytes.
+
+const RAW: &str = "op";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsmError {
+    // 1 based, counting characters.
+    pub line: usize,
+    pub col: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.col, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+// Assembled scripts along with the label each one was given.
+#[derive(Debug, PartialEq)]
+pub struct Listing {
+    pub labels: Vec<String>,
+    pub scripts: Vec<Script>,
+}
+
+impl Listing {
+    // The script index `label` names.
+    pub fn index(&self, label: &str) -> Option<usize> {
+        self.labels.iter().position(|l| l == label)
+    }
+}
+
+// The mnemonic and arguments for `action`.
+fn mnemonic(action: &Action) -> (&'static str, Vec<u8>) {
+    match action {
+        Action::Spell { aoe: false, spell } => ("spell", vec![*spell]),
+        Action::Spell { aoe: true, spell } => ("spell.all", vec![*spell]),
+        Action::EnemyAbility { ability } => ("ability", vec![*ability]),
+        Action::PlayerCommand { command } => ("command", vec![*command]),
+        Action::ChangeCreatureType { types } => ("creature_type", vec![types.to_bytes()[0]]),
+        Action::ChangePhysicalAttackValue { index } => ("attack", vec![*index]),
+        Action::ChangePhysicalDefenseValue { index } => ("defense", vec![*index]),
+        Action::ChangeMagicalDefenseValue { index } => ("magic_defense", vec![*index]),
+        Action::ModifySpeed { data } => ("speed", vec![*data]),
+        Action::SetElementalDefenses { defenses } => {
+            ("elemental_defense", vec![defenses.to_bytes()[0]])
+        }
+        Action::SetSpellPower { power } => ("spell_power", vec![*power]),
+        Action::SetWeakness { weaknesses } => ("weakness", vec![weaknesses.to_bytes()[0]]),
+        Action::SetSprite { index } => ("sprite", vec![*index]),
+        Action::ShowMessage {
+            suppress_next: false,
+            index,
+        } => ("message", vec![*index]),
+        Action::ShowMessage {
+            suppress_next: true,
+            index,
+        } => ("message.suppress", vec![*index]),
+        Action::ChangeMusic { index } => ("music", vec![*index]),
+        Action::IncrementConditionFlag => ("flag.increment", vec![]),
+        Action::SetConditionFlag { value } => ("flag.set", vec![*value]),
+        Action::SetReaction { value } => ("reaction", vec![*value]),
+        Action::DarkenScreen { value } => ("darken", vec![*value]),
+        Action::DebugDisplay { value } => ("debug", vec![*value]),
+        Action::Target { value } => ("target", vec![*value]),
+        Action::ChainInto => ("chain.into", vec![]),
+        Action::EndChain => ("chain.end", vec![]),
+        Action::StartChain => ("chain.start", vec![]),
+        Action::Wait => ("wait", vec![]),
+        Action::Unknown { opcode, args } => (RAW, [&[*opcode], &args[..]].concat()),
+    }
+}
+
+// The action for `mnemonic` given its argument, if the mnemonic takes one.
+// `Err(true)` means the mnemonic needs an argument, `Err(false)` that it
+// doesn't exist.
+fn action(mnemonic: &str, arg: Option<u8>) -> Result<Action, bool> {
+    let action = match (mnemonic, arg) {
+        ("flag.increment", None) => Action::IncrementConditionFlag,
+        ("chain.into", None) => Action::ChainInto,
+        ("chain.end", None) => Action::EndChain,
+        ("chain.start", None) => Action::StartChain,
+        ("wait", None) => Action::Wait,
+        (_, Some(v)) => match mnemonic {
+            "spell" => Action::Spell {
+                aoe: false,
+                spell: v,
+            },
+            "spell.all" => Action::Spell {
+                aoe: true,
+                spell: v,
+            },
+            "ability" => Action::EnemyAbility { ability: v },
+            "command" => Action::PlayerCommand { command: v },
+            "creature_type" => Action::ChangeCreatureType {
+                types: CreatureTypeSet::from_bytes([v]),
+            },
+            "attack" => Action::ChangePhysicalAttackValue { index: v },
+            "defense" => Action::ChangePhysicalDefenseValue { index: v },
+            "magic_defense" => Action::ChangeMagicalDefenseValue { index: v },
+            "speed" => Action::ModifySpeed { data: v },
+            "elemental_defense" => Action::SetElementalDefenses {
+                defenses: StatusSet::from_bytes([v, 0, 0]),
+            },
+            "spell_power" => Action::SetSpellPower { power: v },
+            "weakness" => Action::SetWeakness {
+                weaknesses: WeaknessSet::from_bytes([v]),
+            },
+            "sprite" => Action::SetSprite { index: v },
+            "message" => Action::ShowMessage {
+                suppress_next: false,
+                index: v,
+            },
+            "message.suppress" => Action::ShowMessage {
+                suppress_next: true,
+                index: v,
+            },
+            "music" => Action::ChangeMusic { index: v },
+            "flag.set" => Action::SetConditionFlag { value: v },
+            "reaction" => Action::SetReaction { value: v },
+            "darken" => Action::DarkenScreen { value: v },
+            "debug" => Action::DebugDisplay { value: v },
+            "target" => Action::Target { value: v },
+            _ => return Err(false),
+        },
+        (_, None) => {
+            return Err(action(mnemonic, Some(0)).is_ok());
+        }
+    };
+
+    Ok(action)
+}
+
+// The label `disassemble` gives script `index`.
+pub fn label(index: usize) -> String {
+    format!("script_{:02x}", index)
+}
+
+// Writes `scripts` in the text format.
+pub fn disassemble(scripts: &[Script]) -> String {
+    let mut s = String::new();
+    for (i, script) in scripts.iter().enumerate() {
+        if i > 0 {
+            s.push('\n');
+        }
+        s.push_str(&format!("{}:\n", label(i)));
+        for action in &script.actions {
+            let (m, args) = mnemonic(action);
+            s.push_str(&format!("    {}", m));
+            for arg in args {
+                s.push_str(&format!(" {:#04x}", arg));
+            }
+            s.push('\n');
+        }
+    }
+    s
+}
+
+fn parse_byte(word: &str) -> Option<u8> {
+    if let Some(hex) = word.strip_prefix("0x") {
+        u8::from_str_radix(hex, 16).ok()
+    } else {
+        word.parse().ok()
+    }
+}
+
+// Splits `line` into words with their 1 based character columns.
+fn words(line: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (col, (i, c)) in line.char_indices().enumerate() {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some((col + 1, i)),
+            (Some((col, s)), true) => {
+                words.push((col, &line[s..i]));
+                start = None;
+            }
+            _ => (),
+        }
+    }
+    if let Some((col, s)) = start {
+        words.push((col, &line[s..]));
+    }
+    words
+}
+
+// Assembles the text format back into scripts.  Spans are relative to the
+// start of the first script as if the scripts had been encoded back to back.
+pub fn assemble(text: &str) -> Result<Listing, AsmError> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut label_lines: HashMap<String, usize> = HashMap::new();
+    let mut scripts: Vec<Script> = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line_no = i + 1;
+        let error = |col: usize, message: String| AsmError {
+            line: line_no,
+            col,
+            message,
+        };
+        let code = match line.find(';') {
+            Some(end) => &line[..end],
+            None => line,
+        };
+        let words = words(code);
+        let (col, first) = match words.first() {
+            Some(&word) => word,
+            None => continue,
+        };
+
+        if let Some(name) = first.strip_suffix(':') {
+            if name.is_empty() {
+                return Err(error(col, "empty label".to_string()));
+            }
+            if let Some(line) = label_lines.get(name) {
+                return Err(error(
+                    col,
+                    format!("label {} already defined on line {}", name, line),
+                ));
+            }
+            if let Some(&(col, word)) = words.get(1) {
+                return Err(error(col, format!("unexpected {:?} after label", word)));
+            }
+            label_lines.insert(name.to_string(), line_no);
+            labels.push(name.to_string());
+            scripts.push(Script {
+                actions: Vec::new(),
+                span: Span::default(),
+            });
+            continue;
+        }
+
+        let script = match scripts.last_mut() {
+            Some(script) => script,
+            None => return Err(error(col, "action before the first label".to_string())),
+        };
+        let mut args = Vec::new();
+        for &(col, word) in &words[1..] {
+            args.push(
+                parse_byte(word)
+                    .ok_or_else(|| error(col, format!("expected a byte, found {:?}", word)))?,
+            );
+        }
+        let action = if first == RAW {
+            match args.split_first() {
+                Some((&opcode, args)) => Action::Unknown {
+                    opcode,
+                    args: args.to_vec(),
+                },
+                None => return Err(error(col, format!("{} needs an opcode", first))),
+            }
+        } else {
+            if let Some(&(col, word)) = words.get(2) {
+                return Err(error(col, format!("unexpected {:?}", word)));
+            }
+            let arg = args.first().cloned();
+            match action(first, arg) {
+                Ok(action) => action,
+                Err(true) => return Err(error(col, format!("{} needs an argument", first))),
+                Err(false) if arg.is_some() && action(first, None).is_ok() => {
+                    return Err(error(words[1].0, format!("{} takes no argument", first)))
+                }
+                Err(false) => return Err(error(col, format!("unknown mnemonic {:?}", first))),
+            }
+        };
+        if action.encode().is_err() {
+            let col = words.get(1).map_or(col, |&(col, _)| col);
+            let message = match action {
+                Action::Unknown { .. } => "opcode isn't unknown or has the wrong arguments",
+                _ => "argument out of range",
+            };
+            return Err(error(col, format!("{} for {}", message, first)));
+        }
+        script.actions.push(Spanned::new(Span::default(), action));
+    }
+
+    script::relocate(&mut scripts, 0);
+
+    Ok(Listing { labels, scripts })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::rom_map::RomMap;
+    use super::super::super::test_utils;
+    use super::*;
+
+    #[test]
+    fn round_trip_test() {
+        let data = [
+            0x00, 0x30, 0x31, 0x5e, 0x5f, 0xbf, 0xc0, 0xe7, 0xe8, 0x80, 0xe9, 0x01, 0xea, 0x02,
+            0xeb, 0x03, 0xec, 0x04, 0xed, 0x41, 0xee, 0x06, 0xef, 0x02, 0xf0, 0x08, 0xf1, 0x09,
+            0xf2, 0x0a, 0xf3, 0x0b, 0xf4, 0x01, 0xf4, 0xcf, 0xf5, 0x80, 0xf7, 0x0c, 0xf8, 0x0d,
+            0xf9, 0x0e, 0xfb, 0xfc, 0xfd, 0xfe, 0xff, 0xff, 0x55, 0xff,
+        ];
+        let scripts = script::parse(&data).unwrap();
+        let text = disassemble(&scripts);
+        assert!(text.starts_with("script_00:\n    spell 0x00\n    spell 0x30\n"));
+        assert!(text.contains("\nscript_01:\n\nscript_02:\n    spell.all 0x25\n"));
+
+        let listing = assemble(&text).unwrap();
+        assert_eq!(scripts, listing.scripts);
+        assert_eq!(Some(2), listing.index("script_02"));
+        assert_eq!(data.to_vec(), script::encode(&listing.scripts).unwrap());
+    }
+
+    #[test]
+    fn unknown_test() {
+        let data = [0xf6, 0x12, 0xf4, 0x4f, 0xfe, 0xff];
+        let scripts = script::parse(&data).unwrap();
+        let text = disassemble(&scripts);
+        assert_eq!(
+            "script_00:\n    op 0xf6 0x12\n    op 0xf4 0x4f\n    wait\n",
+            text
+        );
+        assert_eq!(scripts, assemble(&text).unwrap().scripts);
+    }
+
+    #[test]
+    fn assemble_test() {
+        let listing = assemble(
+            "; Milon\nopening:\n  message 3 ; taunt\n\tchain.start\n\n  spell.all 0x25\nidle:\n",
+        )
+        .unwrap();
+        assert_eq!(vec!["opening", "idle"], listing.labels);
+        assert_eq!(
+            vec![0xf1, 0x03, 0xfd, 0x55, 0xff, 0xff],
+            script::encode(&listing.scripts).unwrap()
+        );
+        assert_eq!(Span::new(5, 1), listing.scripts[1].span);
+
+        let error = |text: &str| assemble(text).unwrap_err().to_string();
+        assert_eq!("1:1: action before the first label", error("wait"));
+        assert_eq!("2:3: unknown mnemonic \"wiat\"", error("a:\n  wiat"));
+        assert_eq!(
+            "2:9: expected a byte, found \"0x100\"",
+            error("a:\n  spell 0x100")
+        );
+        assert_eq!(
+            "2:13: argument out of range for spell.all",
+            error("a:\n  spell.all 0x30")
+        );
+        assert_eq!("2:1: ability needs an argument", error("a:\nability"));
+        assert_eq!("2:6: wait takes no argument", error("a:\nwait 1"));
+        assert_eq!("2:10: unexpected \"2\"", error("a:\nsprite 1 2"));
+        assert_eq!(
+            "3:1: label a already defined on line 1",
+            error("a:\nwait\na:")
+        );
+        assert_eq!("2:1: op needs an opcode", error("a:\nop"));
+        assert_eq!(
+            "2:4: opcode isn't unknown or has the wrong arguments for op",
+            error("a:\nop 0xf4 0x01")
+        );
+        assert_eq!(
+            "2:4: opcode isn't unknown or has the wrong arguments for op",
+            error("a:\nop 0xf6")
+        );
+    }
+
+    #[cfg_attr(feature = "ci_tests", ignore)]
+    #[test]
+    fn rom_round_trip_test() {
+        let data = test_utils::load_rom().unwrap();
+        let map = RomMap::us();
+        for region in &[map.earth_scripts(), map.moon_scripts()] {
+            let scripts = script::parse(region.slice(&data).unwrap()).unwrap();
+            let listing = assemble(&disassemble(&scripts)).unwrap();
+            assert_eq!(scripts, listing.scripts, "{}", region);
+        }
+    }
+}
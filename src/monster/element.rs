@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+
+use super::{Monster, Status, Weakness};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum Element {
+    Light,
+    Dark,
+    Lightning,
+    Ice,
+    Fire,
+}
+
+pub const ELEMENTS: [Element; 5] = [
+    Element::Light,
+    Element::Dark,
+    Element::Lightning,
+    Element::Ice,
+    Element::Fire,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum ElementResponse {
+    Normal,
+    Weak,
+    Resist,
+    Immune,
+    Absorb,
+}
+
+impl Element {
+    pub fn status(self) -> Status {
+        match self {
+            Element::Light => Status::Light,
+            Element::Dark => Status::Dark,
+            Element::Lightning => Status::Lightning,
+            Element::Ice => Status::Ice,
+            Element::Fire => Status::Fire,
+        }
+    }
+
+    pub fn weakness(self) -> Weakness {
+        match self {
+            Element::Light => Weakness::Light,
+            Element::Dark => Weakness::Dark,
+            Element::Lightning => Weakness::Lightning,
+            Element::Ice => Weakness::Ice,
+            Element::Fire => Weakness::Fire,
+        }
+    }
+}
+
+impl Monster {
+    // The first defense status byte holds modifier flags which apply to the
+    // element flags in the same byte.  Absorb and immunity override a
+    // weakness while resistance and weakness cancel each other out.
+    pub fn resistances(&self) -> BTreeMap<Element, ElementResponse> {
+        let defenses = self.defense_statuses.unwrap_or_default();
+        let weaknesses = self.weaknesses.unwrap_or_default();
+
+        let modifier = if defenses.contains(Status::AbsorbsElements) {
+            Some(ElementResponse::Absorb)
+        } else if defenses.contains(Status::ImmuneToElements) {
+            Some(ElementResponse::Immune)
+        } else if defenses.contains(Status::ResistsElements) {
+            Some(ElementResponse::Resist)
+        } else {
+            None
+        };
+
+        let mut resistances = BTreeMap::new();
+        for &element in ELEMENTS.iter() {
+            let defended = match modifier {
+                Some(m) if defenses.contains(element.status()) => m,
+                _ => ElementResponse::Normal,
+            };
+            let weak = weaknesses.contains(element.weakness());
+
+            let response = match (defended, weak) {
+                (ElementResponse::Normal, true) => ElementResponse::Weak,
+                (ElementResponse::Resist, true) => ElementResponse::Normal,
+                (r, _) => r,
+            };
+            resistances.insert(element, response);
+        }
+
+        resistances
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{StatusSet, WeaknessSet};
+    use super::*;
+
+    fn monster(defenses: u8, weaknesses: u8) -> Monster {
+        Monster {
+            defense_statuses: Some(StatusSet::from_bytes([defenses, 0, 0])),
+            weaknesses: Some(WeaknessSet::from_bytes([weaknesses])),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn no_defenses_test() {
+        let resistances = Monster::default().resistances();
+        assert_eq!(5, resistances.len());
+        assert!(resistances.values().all(|&r| r == ElementResponse::Normal));
+    }
+
+    #[test]
+    fn modifier_test() {
+        // Absorbs ice, weak to light and fire (Milon Z.).
+        let resistances = monster(0x42, 0x31).resistances();
+        assert_eq!(ElementResponse::Absorb, resistances[&Element::Ice]);
+        assert_eq!(ElementResponse::Weak, resistances[&Element::Light]);
+        assert_eq!(ElementResponse::Weak, resistances[&Element::Fire]);
+        assert_eq!(ElementResponse::Normal, resistances[&Element::Dark]);
+
+        let resistances = monster(0x88, 0x00).resistances();
+        assert_eq!(ElementResponse::Immune, resistances[&Element::Dark]);
+        assert_eq!(ElementResponse::Normal, resistances[&Element::Fire]);
+    }
+
+    #[test]
+    fn weakness_combination_test() {
+        let resistances = monster(0x23, 0x03).resistances();
+        assert_eq!(ElementResponse::Normal, resistances[&Element::Fire]);
+        assert_eq!(ElementResponse::Normal, resistances[&Element::Ice]);
+
+        let resistances = monster(0x81, 0x01).resistances();
+        assert_eq!(ElementResponse::Immune, resistances[&Element::Fire]);
+    }
+}
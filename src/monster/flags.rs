@@ -0,0 +1,265 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::iter::FromIterator;
+use std::ops::{BitAnd, BitOr, Sub};
+
+use super::{CreatureType, Status, Weakness};
+
+// Declares a set of flags stored as a big endian bitfield in the ROM.  The
+// flags are listed from the most significant bit of the first byte down to
+// the least significant bit of the last byte.
+macro_rules! flag_set {
+    ($name:ident, $flag:ident, $bytes:expr, [$($variant:ident),* $(,)*]) => {
+        #[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+        pub struct $name(u32);
+
+        impl $name {
+            const FLAGS: [$flag; $bytes * 8] = [$($flag::$variant),*];
+
+            fn mask(flag: $flag) -> u32 {
+                let pos = Self::FLAGS.iter().position(|f| *f == flag).unwrap();
+                1 << ($bytes * 8 - 1 - pos)
+            }
+
+            pub fn new() -> $name {
+                $name(0)
+            }
+
+            pub fn from_bytes(data: [u8; $bytes]) -> $name {
+                $name(data.iter().fold(0, |acc, &b| (acc << 8) | b as u32))
+            }
+
+            pub fn to_bytes(&self) -> [u8; $bytes] {
+                let mut data = [0; $bytes];
+                for (i, b) in data.iter_mut().enumerate() {
+                    *b = (self.0 >> (($bytes - 1 - i) * 8)) as u8;
+                }
+                data
+            }
+
+            pub fn contains(&self, flag: $flag) -> bool {
+                self.0 & Self::mask(flag) != 0
+            }
+
+            pub fn insert(&mut self, flag: $flag) {
+                self.0 |= Self::mask(flag);
+            }
+
+            pub fn remove(&mut self, flag: $flag) {
+                self.0 &= !Self::mask(flag);
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.0 == 0
+            }
+
+            pub fn len(&self) -> usize {
+                self.0.count_ones() as usize
+            }
+
+            // Iterates over the set flags in ROM bit order.
+            pub fn iter(&self) -> impl Iterator<Item = $flag> {
+                let set = *self;
+                Self::FLAGS.iter().cloned().filter(move |f| set.contains(*f))
+            }
+
+            pub fn union(&self, other: $name) -> $name {
+                $name(self.0 | other.0)
+            }
+
+            pub fn intersection(&self, other: $name) -> $name {
+                $name(self.0 & other.0)
+            }
+
+            pub fn difference(&self, other: $name) -> $name {
+                $name(self.0 & !other.0)
+            }
+        }
+
+        impl std::fmt::Debug for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.debug_set().entries(self.iter()).finish()
+            }
+        }
+
+        impl FromIterator<$flag> for $name {
+            fn from_iter<I: IntoIterator<Item = $flag>>(iter: I) -> $name {
+                let mut set = $name::new();
+                for flag in iter {
+                    set.insert(flag);
+                }
+                set
+            }
+        }
+
+        impl From<$flag> for $name {
+            fn from(flag: $flag) -> $name {
+                $name(Self::mask(flag))
+            }
+        }
+
+        impl BitOr for $name {
+            type Output = $name;
+
+            fn bitor(self, other: $name) -> $name {
+                self.union(other)
+            }
+        }
+
+        impl BitAnd for $name {
+            type Output = $name;
+
+            fn bitand(self, other: $name) -> $name {
+                self.intersection(other)
+            }
+        }
+
+        impl Sub for $name {
+            type Output = $name;
+
+            fn sub(self, other: $name) -> $name {
+                self.difference(other)
+            }
+        }
+
+        // Serialized as the list of set flag names.
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_seq(self.iter())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<$name, D::Error> {
+                Ok(Vec::<$flag>::deserialize(deserializer)?.into_iter().collect())
+            }
+        }
+    };
+}
+
+flag_set!(
+    StatusSet,
+    Status,
+    3,
+    [
+        ImmuneToElements,
+        AbsorbsElements,
+        ResistsElements,
+        Light,
+        Dark,
+        Lightning,
+        Ice,
+        Fire,
+        Death,
+        Stone,
+        Toad,
+        Tiny,
+        Piggy,
+        Mute,
+        Blind,
+        Poison,
+        Curse,
+        Float,
+        Paralyze,
+        Sleep,
+        Charm,
+        Berserk,
+        Petrify,
+        D,
+    ]
+);
+
+flag_set!(
+    WeaknessSet,
+    Weakness,
+    1,
+    [
+        Damage4x,
+        Floating,
+        SpearsArrow,
+        Light,
+        Dark,
+        Lightning,
+        Ice,
+        Fire
+    ]
+);
+
+flag_set!(
+    CreatureTypeSet,
+    CreatureType,
+    1,
+    [Undead, Mage, Slime, Giant, Spirit, Reptile, Machine, Dragon]
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_set_bytes_test() {
+        let set = StatusSet::from_bytes([0x42, 0x01, 0x80]);
+        assert_eq!(
+            vec!(
+                Status::AbsorbsElements,
+                Status::Ice,
+                Status::Poison,
+                Status::Curse
+            ),
+            set.iter().collect::<Vec<_>>()
+        );
+        assert_eq!([0x42, 0x01, 0x80], set.to_bytes());
+        assert_eq!(4, set.len());
+    }
+
+    #[test]
+    fn weakness_set_bytes_test() {
+        let set = WeaknessSet::from_bytes([0x31]);
+        assert_eq!(
+            vec!(Weakness::SpearsArrow, Weakness::Light, Weakness::Fire),
+            set.iter().collect::<Vec<_>>()
+        );
+        assert_eq!([0x31], set.to_bytes());
+    }
+
+    #[test]
+    fn creature_type_set_test() {
+        let mut set = CreatureTypeSet::new();
+        assert!(set.is_empty());
+        set.insert(CreatureType::Undead);
+        set.insert(CreatureType::Dragon);
+        assert!(set.contains(CreatureType::Undead));
+        assert_eq!([0x81], set.to_bytes());
+        set.remove(CreatureType::Undead);
+        assert_eq!([0x01], set.to_bytes());
+    }
+
+    #[test]
+    fn set_ops_test() {
+        let a: StatusSet = vec![Status::Fire, Status::Poison].into_iter().collect();
+        let b: StatusSet = vec![Status::Poison, Status::D].into_iter().collect();
+        assert_eq!(
+            vec!(Status::Fire, Status::Poison, Status::D),
+            (a | b).iter().collect::<Vec<_>>()
+        );
+        assert_eq!(StatusSet::from(Status::Poison), a & b);
+        assert_eq!(StatusSet::from(Status::Fire), a - b);
+    }
+
+    #[test]
+    fn serialize_test() {
+        let set = StatusSet::from_bytes([0x40, 0x01, 0x00]);
+        assert_eq!(
+            "[\"AbsorbsElements\",\"Poison\"]",
+            serde_json::to_string(&set).unwrap()
+        );
+        assert_eq!(
+            set,
+            serde_json::from_str("[\"Poison\",\"AbsorbsElements\"]").unwrap()
+        );
+        assert_eq!(
+            WeaknessSet::new(),
+            serde_json::from_str::<WeaknessSet>("[]").unwrap()
+        );
+        assert!(serde_json::from_str::<CreatureTypeSet>("[\"Plant\"]").is_err());
+    }
+}
@@ -1,25 +1,38 @@
 pub mod ai;
+pub mod asm;
+mod element;
+mod flags;
 pub mod script;
 
-use super::rom_map;
-use super::string;
+pub use self::element::{Element, ElementResponse, ELEMENTS};
+pub use self::flags::{CreatureTypeSet, StatusSet, WeaknessSet};
 
-use std::error::Error;
+use super::addr::{read_ptr16, FileOffset};
+use super::error::slice;
+use super::region::Region;
+use super::rom_map::RomMap;
+use super::span::Span;
+use super::string::CharTable;
+use super::Error;
 
-#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Stats {
     pub base: u8,
     pub mult: u8,
     pub rate: u8,
 }
 
-#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Speed {
     pub min: u8,
     pub max: u8,
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Status {
     ImmuneToElements,
     AbsorbsElements,
@@ -47,7 +60,7 @@ pub enum Status {
     D,
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Weakness {
     Damage4x,
     Floating,
@@ -59,7 +72,7 @@ pub enum Weakness {
     Fire,
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum CreatureType {
     Undead,
     Mage,
@@ -71,7 +84,8 @@ pub enum CreatureType {
     Dragon,
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct DropTable {
     pub common: u8,
     pub uncommon: u8,
@@ -79,7 +93,8 @@ pub struct DropTable {
     pub very_rare: u8,
 }
 
-#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Monster {
     pub index: usize,
     pub is_boss: bool,
@@ -92,12 +107,32 @@ pub struct Monster {
     pub drop_rate: u8,
     pub drop_table_index: u8,
     pub attack_seq_group: u8,
-    pub attack_statuses: Vec<Status>,
-    pub defense_statuses: Vec<Status>,
-    pub weaknesses: Vec<Weakness>,
-    pub spell_power: u8,
-    pub creature_types: Vec<CreatureType>,
-    pub reflex_attack_seq: u8,
+    // The following fields are only present in the ROM when their bit in the
+    // extension flag byte is set.
+    pub attack_statuses: Option<StatusSet>,
+    pub defense_statuses: Option<StatusSet>,
+    pub weaknesses: Option<WeaknessSet>,
+    pub spell_power: Option<u8>,
+    pub creature_types: Option<CreatureTypeSet>,
+    pub reflex_attack_seq: Option<u8>,
+    // Where the record was parsed from.  Ignored when encoding, so JSON can
+    // leave it out.
+    #[serde(default)]
+    pub span: Span,
+}
+
+// A monster joined with all of the tables it indexes.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ResolvedMonster {
+    pub name: String,
+    pub monster: Monster,
+    pub xp: u16,
+    pub gp: u16,
+    pub physical_attack: Stats,
+    pub physical_defense: Stats,
+    pub magical_defense: Stats,
+    pub speed: Speed,
+    pub drop_table: DropTable,
 }
 
 #[derive(Debug, Serialize)]
@@ -110,240 +145,327 @@ pub struct MonsterData {
     pub speed_table: Vec<Speed>,
     pub drop_tables: Vec<DropTable>,
     pub ai: ai::Ai,
+    // The layout the tables were parsed from and are written back to.
+    pub map: RomMap,
+    // The encoding names were decoded with and are encoded back with.
+    #[serde(skip)]
+    pub char_table: CharTable,
 }
 
-pub fn parse(data: &[u8]) -> Result<MonsterData, Box<Error>> {
+pub const MONSTER_NAME_LEN: usize = 8;
+
+pub fn parse(data: &[u8], map: &RomMap, char_table: &CharTable) -> Result<MonsterData, Error> {
     let mut name_table = Vec::new();
-    let gp_table: Vec<u16>;
-    let xp_table: Vec<u16>;
-    let monster_offset_table: Vec<u16>;
     let mut stat_table = Vec::new();
     let mut speed_table = Vec::new();
     let mut drop_table = Vec::new();
 
-    for addr in (rom_map::MONSTER_NAME_TABLE..rom_map::MONSTER_NAME_TABLE_END).step_by(8) {
-        name_table.push(string::decode(&data[addr..addr + 8])?);
+    for addr in map.monster_names().offsets(MONSTER_NAME_LEN) {
+        let name = slice(data, "monster names", addr, MONSTER_NAME_LEN)?;
+        // Names are decoded losslessly so glyphs added by hacks survive
+        // being written back.
+        name_table.push(char_table.decode_lossless(name));
     }
 
-    gp_table = parse_u16_table(
-        &data[rom_map::MONSTER_GP_TABLE..],
-        rom_map::MONSTER_GP_TABLE_ENTRIES,
-    );
-    xp_table = parse_u16_table(
-        &data[rom_map::MONSTER_XP_TABLE..],
-        rom_map::MONSTER_XP_TABLE_ENTRIES,
-    );
-    monster_offset_table = parse_u16_table(
-        &data[rom_map::MONSTER_OFFSET_TABLE..],
-        rom_map::MONSTER_OFFSET_TABLE_ENTRIES,
-    );
+    let gp_table = parse_u16_table(data, map.monster_gp())?;
+    let xp_table = parse_u16_table(data, map.monster_xp())?;
+    let monster_addrs = parse_monster_addrs(data, map)?;
 
-    for addr in (rom_map::MONSTER_STAT_TABLE..rom_map::MONSTER_STAT_TABLE_END).step_by(3) {
-        stat_table.push(parse_stats(&data[addr..]));
+    for addr in map.monster_stats().offsets(3) {
+        stat_table.push(parse_stats(data, addr)?);
     }
 
-    for addr in (rom_map::MONSTER_SPEED_TABLE..rom_map::MONSTER_SPEED_TABLE_END).step_by(2) {
+    for addr in map.monster_speeds().offsets(2) {
+        let speed = slice(data, "monster speeds", addr, 2)?;
         speed_table.push(Speed {
-            min: data[addr],
-            max: data[addr + 1],
+            min: speed[0],
+            max: speed[1],
         });
     }
 
-    for addr in (rom_map::MONSTER_DROP_TABLE..rom_map::MONSTER_DROP_TABLE_END).step_by(4) {
-        drop_table.push(parse_drop_table(&data[addr..]));
+    for addr in map.monster_drops().offsets(4) {
+        drop_table.push(parse_drop_table(data, addr)?);
     }
 
     let mut monsters = Vec::new();
-    for (index, &offset) in monster_offset_table.iter().enumerate() {
-        let addr = rom_map::MONSTER_INFO_OFFSET + (offset as usize);
-        let mut monster = parse_monster(&data[addr..]);
+    for (index, &addr) in monster_addrs.iter().enumerate() {
+        let mut monster = parse_monster(data, addr)?;
         monster.index = index;
 
         monsters.push(monster);
     }
 
-    let ai = ai::parse(data)?;
+    let ai = ai::parse(data, map)?;
 
     Ok(MonsterData {
-        monsters: monsters,
-        name_table: name_table,
-        gp_table: gp_table,
-        xp_table: xp_table,
-        stat_table: stat_table,
-        speed_table: speed_table,
+        monsters,
+        name_table,
+        gp_table,
+        xp_table,
+        stat_table,
+        speed_table,
         drop_tables: drop_table,
-        ai: ai,
+        ai,
+        map: map.clone(),
+        char_table: char_table.clone(),
     })
 }
 
-fn parse_u16(data: &[u8]) -> u16 {
-    (data[0] as u16) + ((data[1] as u16) << 8)
+fn lookup<T: Clone>(table: &[T], index: usize, region: Region) -> Result<T, Error> {
+    match table.get(index) {
+        Some(entry) => Ok(entry.clone()),
+        None => Err(Error::IndexOutOfRange {
+            table: region.name,
+            offset: region.start,
+            index,
+            len: table.len(),
+        }),
+    }
 }
 
-fn parse_u16_table(data: &[u8], entries: usize) -> Vec<u16> {
-    let mut values = Vec::new();
-    for i in 0..entries {
-        values.push(parse_u16(&data[i * 2..]))
+impl MonsterData {
+    // Looks up a monster and every table entry it references.
+    pub fn resolved(&self, index: usize) -> Result<ResolvedMonster, Error> {
+        let monster = lookup(&self.monsters, index, self.map.monster_offsets())?;
+        let name = lookup(&self.name_table, index, self.map.monster_names())?;
+        let stat = |i: u8| lookup(&self.stat_table, i as usize, self.map.monster_stats());
+
+        Ok(ResolvedMonster {
+            name: name.trim().to_string(),
+            xp: lookup(&self.xp_table, index, self.map.monster_xp())?,
+            gp: lookup(&self.gp_table, index, self.map.monster_gp())?,
+            physical_attack: stat(monster.physical_attack_index)?,
+            physical_defense: stat(monster.physical_defense_index)?,
+            magical_defense: stat(monster.magical_defense_index)?,
+            speed: lookup(
+                &self.speed_table,
+                monster.speed_index as usize,
+                self.map.monster_speeds(),
+            )?,
+            drop_table: lookup(
+                &self.drop_tables,
+                monster.drop_table_index as usize,
+                self.map.monster_drops(),
+            )?,
+            monster,
+        })
     }
 
-    values
+    // Writes the monster tables back into a ROM image.  Monster records are
+    // repacked (identical records are shared) from the start of the monster
+    // record region and the offset table is regenerated.  Nothing is written
+    // if any table doesn't fit its region.
+    pub fn write_to(&self, data: &mut [u8]) -> Result<(), Error> {
+        let offsets_region = self.map.monster_offsets();
+        let entries = offsets_region.entries(2);
+        if self.monsters.len() != entries {
+            return Err(Error::TableSizeMismatch {
+                table: offsets_region.name,
+                offset: offsets_region.start,
+                expected: entries,
+                found: self.monsters.len(),
+            });
+        }
+
+        let mut names = Vec::new();
+        for (i, name) in self.name_table.iter().enumerate() {
+            let addr = self.map.monster_name_table + i * MONSTER_NAME_LEN;
+            names.push(
+                self.char_table
+                    .encode_fixed(name, MONSTER_NAME_LEN)
+                    .map_err(|e| e.relocate("monster names", addr))?,
+            );
+        }
+
+        let records_region = self.map.monster_records();
+        let mut records = Vec::new();
+        let mut offsets = Vec::new();
+        let mut packed: HashMap<Vec<u8>, u16> = HashMap::new();
+        for monster in &self.monsters {
+            let record = encode_monster(monster)
+                .map_err(|e| e.relocate("monster records", records_region.start + records.len()))?;
+            let offset = match packed.get(&record) {
+                Some(&offset) => offset,
+                None => {
+                    // Records are found through 16 bit pointers into the
+                    // info bank so every byte has to stay inside it.
+                    let start = records_region.start + records.len();
+                    for &addr in &[start, start + record.len() - 1] {
+                        if FileOffset(addr).bank() != self.map.monster_info_bank {
+                            return Err(Error::WrongBank {
+                                table: records_region.name,
+                                offset: addr,
+                                bank: self.map.monster_info_bank,
+                            });
+                        }
+                    }
+                    let offset = FileOffset(start).to_snes_addr().offset();
+                    records.extend_from_slice(&record);
+                    packed.insert(record, offset);
+                    offset
+                }
+            };
+            offsets.push(offset);
+        }
+        if records.len() > records_region.len {
+            return Err(Error::TableOverflow {
+                table: records_region.name,
+                offset: records_region.start,
+                len: records.len(),
+                capacity: records_region.len,
+            });
+        }
+
+        let names = table_bytes(
+            self.map.monster_names(),
+            &names,
+            MONSTER_NAME_LEN,
+            |name, out| out.extend_from_slice(name),
+        )?;
+        let gp = table_bytes(self.map.monster_gp(), &self.gp_table, 2, |&v, out| {
+            encode_u16(v, out)
+        })?;
+        let xp = table_bytes(self.map.monster_xp(), &self.xp_table, 2, |&v, out| {
+            encode_u16(v, out)
+        })?;
+        let stats = table_bytes(self.map.monster_stats(), &self.stat_table, 3, encode_stats)?;
+        let speeds = table_bytes(
+            self.map.monster_speeds(),
+            &self.speed_table,
+            2,
+            |speed, out| out.extend_from_slice(&[speed.min, speed.max]),
+        )?;
+        let drops = table_bytes(
+            self.map.monster_drops(),
+            &self.drop_tables,
+            4,
+            encode_drop_table,
+        )?;
+        let mut offset_table = Vec::new();
+        for &offset in &offsets {
+            encode_u16(offset, &mut offset_table);
+        }
+
+        let writes = [
+            (self.map.monster_names(), names),
+            (self.map.monster_gp(), gp),
+            (self.map.monster_xp(), xp),
+            (self.map.monster_stats(), stats),
+            (self.map.monster_speeds(), speeds),
+            (self.map.monster_drops(), drops),
+            (offsets_region, offset_table),
+            (records_region, records),
+        ];
+        for (region, bytes) in &writes {
+            if region.start + bytes.len() > data.len() {
+                return Err(Error::TruncatedRom {
+                    table: region.name,
+                    offset: region.start,
+                    len: bytes.len(),
+                });
+            }
+        }
+        for (region, bytes) in &writes {
+            data[region.start..region.start + bytes.len()].copy_from_slice(bytes);
+        }
+
+        Ok(())
+    }
 }
 
-fn parse_stats(data: &[u8]) -> Stats {
-    Stats {
-        mult: data[0],
-        rate: data[1],
-        base: data[2],
+// Encodes a fixed stride table which is parsed with `region.offsets(stride)`.
+fn table_bytes<T, F>(
+    region: Region,
+    entries: &[T],
+    stride: usize,
+    encode: F,
+) -> Result<Vec<u8>, Error>
+where
+    F: Fn(&T, &mut Vec<u8>),
+{
+    let capacity = region.entries(stride);
+    if entries.len() > capacity {
+        return Err(Error::TableOverflow {
+            table: region.name,
+            offset: region.start,
+            len: entries.len(),
+            capacity,
+        });
     }
+
+    let mut data = Vec::new();
+    for entry in entries {
+        encode(entry, &mut data);
+    }
+
+    Ok(data)
 }
 
-fn is_bit_set(data: u8, bit: u8) -> bool {
-    assert!(bit < 8);
-    (data >> bit) & 0x1 == 0x1
+fn parse_u16(data: &[u8]) -> u16 {
+    (data[0] as u16) + ((data[1] as u16) << 8)
 }
 
-fn parse_status(data: &[u8], statuses: &mut Vec<Status>) {
-    if is_bit_set(data[0], 7) {
-        statuses.push(Status::ImmuneToElements);
-    }
-    if is_bit_set(data[0], 6) {
-        statuses.push(Status::AbsorbsElements);
-    }
-    if is_bit_set(data[0], 5) {
-        statuses.push(Status::ResistsElements);
-    }
-    if is_bit_set(data[0], 4) {
-        statuses.push(Status::Light);
-    }
-    if is_bit_set(data[0], 3) {
-        statuses.push(Status::Dark);
-    }
-    if is_bit_set(data[0], 2) {
-        statuses.push(Status::Lightning);
-    }
-    if is_bit_set(data[0], 1) {
-        statuses.push(Status::Ice);
-    }
-    if is_bit_set(data[0], 0) {
-        statuses.push(Status::Fire);
-    }
+fn parse_u16_table(data: &[u8], region: Region) -> Result<Vec<u16>, Error> {
+    Ok(region.slice(data)?.chunks(2).map(parse_u16).collect())
+}
 
-    if is_bit_set(data[1], 7) {
-        statuses.push(Status::Death);
-    }
-    if is_bit_set(data[1], 6) {
-        statuses.push(Status::Stone);
-    }
-    if is_bit_set(data[1], 5) {
-        statuses.push(Status::Toad);
-    }
-    if is_bit_set(data[1], 4) {
-        statuses.push(Status::Tiny);
-    }
-    if is_bit_set(data[1], 3) {
-        statuses.push(Status::Piggy);
-    }
-    if is_bit_set(data[1], 2) {
-        statuses.push(Status::Mute);
-    }
-    if is_bit_set(data[1], 1) {
-        statuses.push(Status::Blind);
-    }
-    if is_bit_set(data[1], 0) {
-        statuses.push(Status::Poison);
+// Returns the ROM offset of each monster's record.
+fn parse_monster_addrs(data: &[u8], map: &RomMap) -> Result<Vec<usize>, Error> {
+    let region = map.monster_offsets();
+    let mut addrs = Vec::new();
+    for at in region.offsets(2) {
+        let addr = read_ptr16(data, at, map.monster_info_bank, region.name)?;
+        addrs.push(addr.0);
     }
 
-    if is_bit_set(data[2], 7) {
-        statuses.push(Status::Curse);
-    }
-    if is_bit_set(data[2], 6) {
-        statuses.push(Status::Float);
-    }
-    if is_bit_set(data[2], 5) {
-        statuses.push(Status::Paralyze);
-    }
-    if is_bit_set(data[2], 4) {
-        statuses.push(Status::Sleep);
-    }
-    if is_bit_set(data[2], 3) {
-        statuses.push(Status::Charm);
-    }
-    if is_bit_set(data[2], 2) {
-        statuses.push(Status::Berserk);
-    }
-    if is_bit_set(data[2], 1) {
-        statuses.push(Status::Petrify);
-    }
-    if is_bit_set(data[2], 0) {
-        statuses.push(Status::D);
-    }
+    Ok(addrs)
 }
 
-fn parse_weakness(data: &[u8], weaknesses: &mut Vec<Weakness>) {
-    if is_bit_set(data[0], 7) {
-        weaknesses.push(Weakness::Damage4x);
-    }
-    if is_bit_set(data[0], 6) {
-        weaknesses.push(Weakness::Floating);
-    }
-    if is_bit_set(data[0], 5) {
-        weaknesses.push(Weakness::SpearsArrow);
-    }
-    if is_bit_set(data[0], 4) {
-        weaknesses.push(Weakness::Light);
-    }
-    if is_bit_set(data[0], 3) {
-        weaknesses.push(Weakness::Dark);
-    }
-    if is_bit_set(data[0], 2) {
-        weaknesses.push(Weakness::Lightning);
-    }
-    if is_bit_set(data[0], 1) {
-        weaknesses.push(Weakness::Ice);
-    }
-    if is_bit_set(data[0], 0) {
-        weaknesses.push(Weakness::Fire);
-    }
+fn parse_stats(data: &[u8], addr: usize) -> Result<Stats, Error> {
+    let data = slice(data, "monster stats", addr, 3)?;
+    Ok(Stats {
+        mult: data[0],
+        rate: data[1],
+        base: data[2],
+    })
 }
 
-fn parse_creature_types(data: &[u8], types: &mut Vec<CreatureType>) {
-    if is_bit_set(data[0], 7) {
-        types.push(CreatureType::Undead);
-    }
-    if is_bit_set(data[0], 6) {
-        types.push(CreatureType::Mage);
-    }
-    if is_bit_set(data[0], 5) {
-        types.push(CreatureType::Slime);
-    }
-    if is_bit_set(data[0], 4) {
-        types.push(CreatureType::Giant);
-    }
-    if is_bit_set(data[0], 3) {
-        types.push(CreatureType::Spirit);
-    }
-    if is_bit_set(data[0], 2) {
-        types.push(CreatureType::Reptile);
-    }
-    if is_bit_set(data[0], 1) {
-        types.push(CreatureType::Machine);
-    }
-    if is_bit_set(data[0], 0) {
-        types.push(CreatureType::Dragon);
-    }
+fn encode_stats(stats: &Stats, data: &mut Vec<u8>) {
+    data.extend_from_slice(&[stats.mult, stats.rate, stats.base]);
 }
 
-fn parse_drop_table(data: &[u8]) -> DropTable {
-    DropTable {
+fn is_bit_set(data: u8, bit: u8) -> bool {
+    assert!(bit < 8);
+    (data >> bit) & 0x1 == 0x1
+}
+
+fn parse_status(data: &[u8]) -> StatusSet {
+    StatusSet::from_bytes([data[0], data[1], data[2]])
+}
+
+fn parse_drop_table(data: &[u8], addr: usize) -> Result<DropTable, Error> {
+    let data = slice(data, "monster drops", addr, 4)?;
+    Ok(DropTable {
         common: data[0],
         uncommon: data[1],
         rare: data[2],
         very_rare: data[3],
-    }
+    })
+}
+
+fn encode_drop_table(drop_table: &DropTable, data: &mut Vec<u8>) {
+    data.extend_from_slice(&[
+        drop_table.common,
+        drop_table.uncommon,
+        drop_table.rare,
+        drop_table.very_rare,
+    ]);
 }
 
-fn parse_monster(data: &[u8]) -> Monster {
+fn parse_monster(rom: &[u8], addr: usize) -> Result<Monster, Error> {
     let mut monster = Monster::default();
+    let data = slice(rom, "monster records", addr, 10)?;
+    let ext = |index, len| slice(rom, "monster records", addr + index, len);
 
     if (data[0] & 0x80) == 0x80 {
         monster.is_boss = true;
@@ -362,48 +484,151 @@ fn parse_monster(data: &[u8]) -> Monster {
         0b10 => 25,
         _ => 100,
     };
-    if monster.drop_rate > 0 {
-        monster.drop_table_index = data[7] & 0x3f;
-    }
+    monster.drop_table_index = data[7] & 0x3f;
 
     monster.attack_seq_group = data[8];
     let ext_byte_flags = data[9];
     let mut index = 10;
 
     if is_bit_set(ext_byte_flags, 7) {
-        parse_status(&data[index..], &mut monster.attack_statuses);
+        monster.attack_statuses = Some(parse_status(ext(index, 3)?));
         index += 3;
     }
 
     if is_bit_set(ext_byte_flags, 6) {
-        parse_status(&data[index..], &mut monster.defense_statuses);
+        monster.defense_statuses = Some(parse_status(ext(index, 3)?));
         index += 3;
     }
 
     if is_bit_set(ext_byte_flags, 5) {
-        parse_weakness(&data[index..], &mut monster.weaknesses);
+        monster.weaknesses = Some(WeaknessSet::from_bytes([ext(index, 1)?[0]]));
         index += 1;
     }
 
     if is_bit_set(ext_byte_flags, 4) {
-        monster.spell_power = data[index];
+        monster.spell_power = Some(ext(index, 1)?[0]);
         index += 1;
     }
 
     if is_bit_set(ext_byte_flags, 3) {
-        parse_creature_types(&data[index..], &mut monster.creature_types);
+        monster.creature_types = Some(CreatureTypeSet::from_bytes([ext(index, 1)?[0]]));
         index += 1;
     }
 
     if is_bit_set(ext_byte_flags, 2) {
-        monster.reflex_attack_seq = data[index];
+        monster.reflex_attack_seq = Some(ext(index, 1)?[0]);
+        index += 1;
     }
 
-    monster
+    monster.span = Span::new(addr, index);
+    Ok(monster)
+}
+
+fn encode_u16(value: u16, data: &mut Vec<u8>) {
+    data.push(value as u8);
+    data.push((value >> 8) as u8);
+}
+
+fn set_bit(data: &mut u8, bit: u8) {
+    assert!(bit < 8);
+    *data |= 1 << bit;
+}
+
+// Returns `value` if it fits in `mask`, which covers the field's bits of the
+// byte at `offset` in a monster record.
+fn check_field(field: &'static str, value: u8, mask: u8, offset: usize) -> Result<u8, Error> {
+    if value & !mask != 0 {
+        return Err(Error::UnencodableValue {
+            table: "monster records",
+            offset,
+            field,
+            value: value as usize,
+        });
+    }
+    Ok(value)
+}
+
+// Encodes a monster into its variable length ROM record.  This is the
+// inverse of `parse_monster`.  The extension flag byte is recomputed from
+// the optional fields which are present.  Values which don't fit their bits
+// are errors rather than being truncated.  Errors report offsets relative to
+// the record.
+pub fn encode_monster(monster: &Monster) -> Result<Vec<u8>, Error> {
+    let mut data = Vec::new();
+
+    let mut level = check_field("level", monster.level, 0x7f, 0)?;
+    if monster.is_boss {
+        level |= 0x80;
+    }
+    data.push(level);
+    encode_u16(monster.max_hp, &mut data);
+
+    data.push(monster.physical_attack_index);
+    data.push(monster.physical_defense_index);
+    data.push(monster.magical_defense_index);
+    data.push(check_field("speed index", monster.speed_index, 0x3f, 6)?);
+
+    let drop_rate: u8 = match monster.drop_rate {
+        0 => 0b00,
+        5 => 0b01,
+        25 => 0b10,
+        100 => 0b11,
+        rate => {
+            return Err(Error::UnencodableValue {
+                table: "monster records",
+                offset: 7,
+                field: "drop rate",
+                value: rate as usize,
+            })
+        }
+    };
+    let drop_table_index = check_field("drop table index", monster.drop_table_index, 0x3f, 7)?;
+    data.push((drop_rate << 6) | drop_table_index);
+
+    data.push(monster.attack_seq_group);
+
+    let mut ext_byte_flags = 0;
+    let mut ext = Vec::new();
+
+    if let Some(statuses) = &monster.attack_statuses {
+        set_bit(&mut ext_byte_flags, 7);
+        ext.extend_from_slice(&statuses.to_bytes());
+    }
+
+    if let Some(statuses) = &monster.defense_statuses {
+        set_bit(&mut ext_byte_flags, 6);
+        ext.extend_from_slice(&statuses.to_bytes());
+    }
+
+    if let Some(weaknesses) = &monster.weaknesses {
+        set_bit(&mut ext_byte_flags, 5);
+        ext.extend_from_slice(&weaknesses.to_bytes());
+    }
+
+    if let Some(spell_power) = monster.spell_power {
+        set_bit(&mut ext_byte_flags, 4);
+        ext.push(spell_power);
+    }
+
+    if let Some(types) = &monster.creature_types {
+        set_bit(&mut ext_byte_flags, 3);
+        ext.extend_from_slice(&types.to_bytes());
+    }
+
+    if let Some(reflex_attack_seq) = monster.reflex_attack_seq {
+        set_bit(&mut ext_byte_flags, 2);
+        ext.push(reflex_attack_seq);
+    }
+
+    data.push(ext_byte_flags);
+    data.extend_from_slice(&ext);
+
+    Ok(data)
 }
 
 #[cfg(test)]
 mod tests {
+    use super::super::addr::SnesAddr;
     use super::super::test_utils;
     use super::*;
 
@@ -416,7 +641,15 @@ mod tests {
     fn parse_u16_table_test() {
         assert_eq!(
             vec!(0xaa55, 0xff00),
-            parse_u16_table(&[0x55, 0xaa, 0x00, 0xff], 2)
+            parse_u16_table(&[0x55, 0xaa, 0x00, 0xff], Region::new("test", 0, 4)).unwrap()
+        );
+        assert_eq!(
+            Err(Error::TruncatedRom {
+                table: "test",
+                offset: 1,
+                len: 4,
+            }),
+            parse_u16_table(&[0x55, 0xaa, 0x00, 0xff], Region::new("test", 1, 4))
         );
     }
 
@@ -428,7 +661,7 @@ mod tests {
                 mult: 0xaa,
                 rate: 0xff,
             },
-            parse_stats(&[0xaa, 0xff, 0x55])
+            parse_stats(&[0xaa, 0xff, 0x55], 0).unwrap()
         );
     }
 
@@ -441,7 +674,7 @@ mod tests {
                 rare: 0xaa,
                 very_rare: 0xff,
             },
-            parse_drop_table(&[0x00, 0x55, 0x0aa, 0xff])
+            parse_drop_table(&[0x00, 0x55, 0x0aa, 0xff], 0).unwrap()
         );
     }
 
@@ -460,14 +693,19 @@ mod tests {
                 drop_rate: 5,
                 drop_table_index: 0x38,
                 attack_seq_group: 0,
-                attack_statuses: vec!(),
-                defense_statuses: vec!(),
-                weaknesses: vec!(),
-                spell_power: 0x0,
-                creature_types: vec!(),
-                reflex_attack_seq: 0x0,
+                attack_statuses: None,
+                defense_statuses: None,
+                weaknesses: None,
+                spell_power: None,
+                creature_types: None,
+                reflex_attack_seq: None,
+                span: Span::new(0, 10),
             },
-            parse_monster(&[0x03, 0x06, 0x00, 0x01, 0x60, 0xa0, 0x02, 0x78, 0x00, 0x00],)
+            parse_monster(
+                &[0x03, 0x06, 0x00, 0x01, 0x60, 0xa0, 0x02, 0x78, 0x00, 0x00],
+                0
+            )
+            .unwrap()
         );
         assert_eq!(
             Monster {
@@ -482,25 +720,455 @@ mod tests {
                 drop_rate: 0,
                 drop_table_index: 0,
                 attack_seq_group: 149,
-                attack_statuses: vec!(Status::Poison),
-                defense_statuses: vec!(Status::AbsorbsElements, Status::Ice),
-                weaknesses: vec!(Weakness::SpearsArrow, Weakness::Light, Weakness::Fire),
-                spell_power: 31,
-                creature_types: vec!(CreatureType::Undead),
-                reflex_attack_seq: 0,
+                attack_statuses: Some(Status::Poison.into()),
+                defense_statuses: Some(
+                    StatusSet::from(Status::AbsorbsElements) | Status::Ice.into()
+                ),
+                weaknesses: Some(
+                    vec!(Weakness::SpearsArrow, Weakness::Light, Weakness::Fire)
+                        .into_iter()
+                        .collect()
+                ),
+                spell_power: Some(31),
+                creature_types: Some(CreatureType::Undead.into()),
+                reflex_attack_seq: None,
+                span: Span::new(0, 19),
             },
-            parse_monster(&[
-                0x8F, 0xB8, 0x0B, 0x16, 0x6B, 0xC0, 0x32, 0x00, 0x95, 0xF8, 0x00, 0x01, 0x00, 0x42,
-                0x00, 0x00, 0x31, 0x1F, 0x80
-            ],)
+            parse_monster(
+                &[
+                    0x8F, 0xB8, 0x0B, 0x16, 0x6B, 0xC0, 0x32, 0x00, 0x95, 0xF8, 0x00, 0x01, 0x00,
+                    0x42, 0x00, 0x00, 0x31, 0x1F, 0x80
+                ],
+                0
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn json_test() {
+        let data = [
+            0x8F, 0xB8, 0x0B, 0x16, 0x6B, 0xC0, 0x32, 0x00, 0x95, 0xF8, 0x00, 0x01, 0x00, 0x42,
+            0x00, 0x00, 0x31, 0x1F, 0x80,
+        ];
+        let monster = parse_monster(&data, 0).unwrap();
+        let json = serde_json::to_string(&monster).unwrap();
+        let parsed: Monster = serde_json::from_str(&json).unwrap();
+        assert_eq!(monster, parsed);
+        assert_eq!(data.to_vec(), encode_monster(&parsed).unwrap());
+
+        // Hand written JSON can leave out the span and name flags in any
+        // order.
+        let json = r#"{
+            "index": 0, "is_boss": false, "level": 3, "max_hp": 6,
+            "physical_attack_index": 1, "physical_defense_index": 96,
+            "magical_defense_index": 160, "speed_index": 2, "drop_rate": 5,
+            "drop_table_index": 56, "attack_seq_group": 0,
+            "attack_statuses": null, "defense_statuses": null,
+            "weaknesses": ["Fire", "Ice"], "spell_power": null,
+            "creature_types": null, "reflex_attack_seq": null
+        }"#;
+        let monster: Monster = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            vec!(0x03, 0x06, 0x00, 0x01, 0x60, 0xa0, 0x02, 0x78, 0x00, 0x20, 0x03),
+            encode_monster(&monster).unwrap()
+        );
+        assert!(serde_json::from_str::<Monster>(&json.replace("level", "lvl")).is_err());
+    }
+
+    #[test]
+    fn parse_truncated_monster_test() {
+        assert_eq!(
+            Err(Error::TruncatedRom {
+                table: "monster records",
+                offset: 1,
+                len: 10,
+            }),
+            parse_monster(&[0x00; 10], 1)
+        );
+        // The extension flags promise defense statuses which aren't there.
+        assert_eq!(
+            Err(Error::TruncatedRom {
+                table: "monster records",
+                offset: 10,
+                len: 3,
+            }),
+            parse_monster(
+                &[0x03, 0x06, 0x00, 0x01, 0x60, 0xa0, 0x02, 0x78, 0x00, 0x40, 0x00],
+                0
+            )
+        );
+    }
+
+    #[test]
+    fn parse_truncated_rom_test() {
+        let map = RomMap::us();
+        assert_eq!(
+            Err(Error::TruncatedRom {
+                table: "monster names",
+                offset: map.monster_name_table,
+                len: 8,
+            }),
+            parse(&[0xff; 0x100], &map, &CharTable::us()).map(|_| ())
+        );
+
+        // An offset table pointing past the end of the ROM.
+        let (mut data, _) = synthetic_monster_data();
+        for b in &mut data[map.monster_names().range()] {
+            *b = 0xff;
+        }
+        data[map.monster_offset_table] = 0xff;
+        data[map.monster_offset_table + 1] = 0xff;
+        let end = SnesAddr::new(map.monster_info_bank, 0xffff)
+            .to_file_offset()
+            .unwrap();
+        data.truncate(end.0 - 0x10);
+        assert_eq!(
+            Some(("monster records", end.0)),
+            parse(&data, &map, &CharTable::us())
+                .err()
+                .map(|e| (e.table(), e.offset()))
+        );
+
+        // An offset which isn't in the ROM half of the bank.
+        data[map.monster_offset_table + 1] = 0x10;
+        assert_eq!(
+            Some(("monster offsets", map.monster_offset_table)),
+            parse(&data, &map, &CharTable::us())
+                .err()
+                .map(|e| (e.table(), e.offset()))
+        );
+    }
+
+    #[test]
+    fn encode_monster_test() {
+        let data = [0x03, 0x06, 0x00, 0x01, 0x60, 0xa0, 0x02, 0x78, 0x00, 0x00];
+        assert_eq!(
+            data.to_vec(),
+            encode_monster(&parse_monster(&data, 0).unwrap()).unwrap()
+        );
+
+        let data = [
+            0x8F, 0xB8, 0x0B, 0x16, 0x6B, 0xC0, 0x32, 0x00, 0x95, 0xF8, 0x00, 0x01, 0x00, 0x42,
+            0x00, 0x00, 0x31, 0x1F, 0x80,
+        ];
+        assert_eq!(
+            data.to_vec(),
+            encode_monster(&parse_monster(&data, 0).unwrap()).unwrap()
+        );
+
+        // Extension fields which are present but empty must still be encoded.
+        let data = [
+            0x01, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xfc, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(
+            data.to_vec(),
+            encode_monster(&parse_monster(&data, 0).unwrap()).unwrap()
+        );
+
+        // Values which don't fit their bits aren't truncated.
+        let error = |monster: Monster| encode_monster(&monster).err();
+        assert_eq!(
+            Some(Error::UnencodableValue {
+                table: "monster records",
+                offset: 6,
+                field: "speed index",
+                value: 0x40,
+            }),
+            error(Monster {
+                speed_index: 0x40,
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            Some(Error::UnencodableValue {
+                table: "monster records",
+                offset: 7,
+                field: "drop rate",
+                value: 50,
+            }),
+            error(Monster {
+                drop_rate: 50,
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            Some(Error::UnencodableValue {
+                table: "monster records",
+                offset: 7,
+                field: "drop table index",
+                value: 0x40,
+            }),
+            error(Monster {
+                drop_table_index: 0x40,
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            Some(Error::UnencodableValue {
+                table: "monster records",
+                offset: 0,
+                field: "level",
+                value: 0x80,
+            }),
+            error(Monster {
+                level: 0x80,
+                ..Default::default()
+            })
         );
     }
 
+    #[cfg_attr(feature = "ci_tests", ignore)]
+    #[test]
+    fn encode_rom_test() {
+        let map = RomMap::us();
+        let data = test_utils::load_rom().unwrap();
+        for addr in parse_monster_addrs(&data, &map).unwrap() {
+            let encoded = encode_monster(&parse_monster(&data, addr).unwrap()).unwrap();
+            assert_eq!(&data[addr..addr + encoded.len()], &encoded[..]);
+        }
+    }
+
+    // Builds a ROM image with one distinct record per monster packed
+    // back to back and returns the matching `MonsterData`.
+    fn synthetic_monster_data() -> (Vec<u8>, MonsterData) {
+        let map = RomMap::us();
+        let entries = map.monster_offsets().entries(2);
+        let mut data = vec![0; 0x80000];
+        let mut monsters = Vec::new();
+        let mut addr = map.monster_offset_table + entries * 2;
+        for index in 0..entries {
+            let monster = Monster {
+                index,
+                level: index as u8 & 0x7f,
+                is_boss: index >= 0x80,
+                ..Default::default()
+            };
+            let record = encode_monster(&monster).unwrap();
+            let offset = FileOffset(addr).to_snes_addr().offset();
+            data[map.monster_offset_table + index * 2] = offset as u8;
+            data[map.monster_offset_table + index * 2 + 1] = (offset >> 8) as u8;
+            data[addr..addr + record.len()].copy_from_slice(&record);
+            addr += record.len();
+            monsters.push(monster);
+        }
+
+        let monster_data = MonsterData {
+            monsters,
+            name_table: vec!["Imp     ".to_string(); entries],
+            gp_table: (0..entries as u16).collect(),
+            xp_table: (0..entries as u16).map(|v| v * 2).collect(),
+            stat_table: vec![Stats::default(); 0xe0],
+            speed_table: vec![Speed { min: 1, max: 2 }; 0x40],
+            drop_tables: vec![DropTable::default(); 0x40],
+            ai: ai::Ai {
+                condition_sets: vec![],
+                conditions: vec![],
+                groups: vec![],
+                earth_scripts: vec![],
+                moon_scripts: vec![],
+            },
+            map,
+            char_table: CharTable::us(),
+        };
+
+        (data, monster_data)
+    }
+
+    #[test]
+    fn parse_unknown_glyph_test() {
+        let map = RomMap::us();
+        let (mut data, monster_data) = synthetic_monster_data();
+        monster_data.write_to(&mut data).unwrap();
+        data[map.monster_name_table + 3] = 0x00;
+
+        let parsed = parse(&data, &map, &CharTable::us()).unwrap();
+        assert_eq!("Imp<00>    ", parsed.name_table[0]);
+
+        let before = data.clone();
+        parsed.write_to(&mut data).unwrap();
+        assert_eq!(before, data);
+    }
+
+    #[test]
+    fn write_to_synthetic_test() {
+        let map = RomMap::us();
+        let (mut data, mut monster_data) = synthetic_monster_data();
+
+        monster_data.gp_table[3] = 0x1234;
+        monster_data.write_to(&mut data).unwrap();
+        assert_eq!(0x1234, parse_u16(&data[map.monster_gp_table + 3 * 2..]));
+        assert_eq!(
+            &[0x4a, 0x68, 0x6b, 0xff, 0xff, 0xff, 0xff, 0xff],
+            &data[map.monster_name_table..map.monster_name_table + 8]
+        );
+
+        // Shrink the region to exactly fit the records so growing a record
+        // doesn't fit.
+        monster_data.map.monster_record_table_len = 0xe0 * 10;
+        let before = data.clone();
+        let original = monster_data.monsters[2].clone();
+        monster_data.monsters[0].spell_power = Some(10);
+        assert_eq!(
+            Err(Error::TableOverflow {
+                table: "monster records",
+                offset: map.monster_record_table,
+                len: 0xe0 * 10 + 1,
+                capacity: 0xe0 * 10,
+            }),
+            monster_data.write_to(&mut data)
+        );
+        assert_eq!(before, data);
+
+        // Sharing identical records makes room for the larger record.
+        monster_data.monsters[2] = monster_data.monsters[1].clone();
+        monster_data.write_to(&mut data).unwrap();
+        let addrs = parse_monster_addrs(&data, &map).unwrap();
+        assert_eq!(addrs[1], addrs[2]);
+        assert_eq!(
+            Some(10),
+            parse_monster(&data, addrs[0]).unwrap().spell_power
+        );
+
+        // The space freed by sharing is still available to later writes.
+        monster_data.monsters[0].spell_power = None;
+        monster_data.write_to(&mut data).unwrap();
+        monster_data.monsters[2] = original;
+        monster_data.write_to(&mut data).unwrap();
+        let addrs = parse_monster_addrs(&data, &map).unwrap();
+        for (monster, addr) in monster_data.monsters.iter().zip(addrs) {
+            assert_eq!(
+                encode_monster(monster).unwrap(),
+                encode_monster(&parse_monster(&data, addr).unwrap()).unwrap()
+            );
+        }
+
+        // Records have to stay in the bank their pointers point into.
+        let before = data.clone();
+        monster_data.map.monster_record_table = 0x80000;
+        assert_eq!(
+            Err(Error::WrongBank {
+                table: "monster records",
+                offset: 0x80000,
+                bank: 0x0e,
+            }),
+            monster_data.write_to(&mut data)
+        );
+        // The second record crosses into the next bank.
+        monster_data.map.monster_record_table = 0x78000 - 0x10;
+        assert_eq!(
+            Err(Error::WrongBank {
+                table: "monster records",
+                offset: 0x78003,
+                bank: 0x0e,
+            }),
+            monster_data.write_to(&mut data)
+        );
+        assert_eq!(before, data);
+        monster_data.map.monster_record_table = map.monster_record_table;
+
+        monster_data.name_table[0] = "Too long name".to_string();
+        assert_eq!(
+            Some("monster names"),
+            monster_data.write_to(&mut data).err().map(|e| e.table())
+        );
+    }
+
+    #[test]
+    fn resolved_test() {
+        let map = RomMap::us();
+        let (_, mut monster_data) = synthetic_monster_data();
+        monster_data.speed_table[1] = Speed { min: 3, max: 4 };
+        monster_data.monsters[5].speed_index = 1;
+
+        let resolved = monster_data.resolved(5).unwrap();
+        assert_eq!("Imp", resolved.name);
+        assert_eq!(5, resolved.gp);
+        assert_eq!(10, resolved.xp);
+        assert_eq!(Speed { min: 3, max: 4 }, resolved.speed);
+        assert_eq!(monster_data.monsters[5], resolved.monster);
+
+        assert!(monster_data.resolved(0xe0).is_err());
+
+        monster_data.stat_table.truncate(0x10);
+        monster_data.monsters[5].magical_defense_index = 0x10;
+        assert_eq!(
+            Err(Error::IndexOutOfRange {
+                table: "monster stats",
+                offset: map.monster_stat_table,
+                index: 0x10,
+                len: 0x10,
+            }),
+            monster_data.resolved(5)
+        );
+    }
+
+    #[cfg_attr(feature = "ci_tests", ignore)]
+    #[test]
+    fn write_to_test() {
+        let map = RomMap::us();
+        let data = test_utils::load_rom().unwrap();
+        let mut monster_data = parse(&data, &map, &CharTable::us()).unwrap();
+
+        // Spans move when records are repacked so compare the encodings.
+        let records =
+            |monsters: &[Monster]| monsters.iter().map(encode_monster).collect::<Vec<_>>();
+
+        let mut written = data.clone();
+        monster_data.write_to(&mut written).unwrap();
+        assert_eq!(
+            records(&parse(&written, &map, &CharTable::us()).unwrap().monsters),
+            records(&monster_data.monsters)
+        );
+
+        // Growing a record must still fit after repacking shared records.
+        monster_data.monsters[0].spell_power = Some(10);
+        monster_data.gp_table[0] = 1234;
+        monster_data.write_to(&mut written).unwrap();
+        let reparsed = parse(&written, &map, &CharTable::us()).unwrap();
+        assert_eq!(Some(10), reparsed.monsters[0].spell_power);
+        assert_eq!(1234, reparsed.gp_table[0]);
+        assert_eq!(
+            records(&monster_data.monsters[1..]),
+            records(&reparsed.monsters[1..])
+        );
+
+        // Records which can't fit must fail without touching the image.
+        for monster in monster_data.monsters.iter_mut() {
+            monster.attack_statuses = Some(Status::Poison.into());
+            monster.defense_statuses = Some(Status::Fire.into());
+            monster.reflex_attack_seq = Some(monster.index as u8);
+        }
+        let before = written.clone();
+        assert!(monster_data.write_to(&mut written).is_err());
+        assert_eq!(before, written);
+    }
+
+    #[cfg_attr(feature = "ci_tests", ignore)]
+    #[test]
+    fn name_round_trip_test() {
+        let map = RomMap::us();
+        let table = CharTable::us();
+        let data = test_utils::load_rom().unwrap();
+        for addr in map.monster_names().offsets(MONSTER_NAME_LEN) {
+            let bytes = &data[addr..addr + MONSTER_NAME_LEN];
+            let name = table.decode_lossless(bytes);
+            assert_eq!(
+                bytes,
+                &table.encode_fixed(&name, MONSTER_NAME_LEN).unwrap()[..],
+                "{:?}",
+                name
+            );
+        }
+    }
+
     #[cfg_attr(feature = "ci_tests", ignore)]
     #[test]
     fn parse_rom_test() {
+        let map = RomMap::us();
         let data = test_utils::load_rom().unwrap();
-        let ff4 = parse(&data).unwrap();
+        let ff4 = parse(&data, &map, &CharTable::us()).unwrap();
 
         let milon = &ff4.monsters[0xa5];
         assert_eq!(
@@ -516,12 +1184,13 @@ mod tests {
                 drop_rate: 0,
                 drop_table_index: 0x0,
                 attack_seq_group: 145,
-                attack_statuses: vec!(),
-                defense_statuses: vec!(),
-                weaknesses: vec!(),
-                spell_power: 14,
-                creature_types: vec!(),
-                reflex_attack_seq: 146
+                attack_statuses: None,
+                defense_statuses: None,
+                weaknesses: None,
+                spell_power: Some(14),
+                creature_types: None,
+                reflex_attack_seq: Some(146),
+                span: Span::new(milon.span.offset, 12),
             },
             *milon
         );
@@ -571,12 +1240,19 @@ mod tests {
                 drop_rate: 0,
                 drop_table_index: 0x0,
                 attack_seq_group: 149,
-                attack_statuses: vec!(Status::Poison),
-                defense_statuses: vec!(Status::AbsorbsElements, Status::Ice),
-                weaknesses: vec!(Weakness::SpearsArrow, Weakness::Light, Weakness::Fire),
-                spell_power: 31,
-                creature_types: vec!(CreatureType::Undead),
-                reflex_attack_seq: 0,
+                attack_statuses: Some(Status::Poison.into()),
+                defense_statuses: Some(
+                    StatusSet::from(Status::AbsorbsElements) | Status::Ice.into()
+                ),
+                weaknesses: Some(
+                    vec!(Weakness::SpearsArrow, Weakness::Light, Weakness::Fire)
+                        .into_iter()
+                        .collect()
+                ),
+                spell_power: Some(31),
+                creature_types: Some(CreatureType::Undead.into()),
+                reflex_attack_seq: None,
+                span: Span::new(milon_z.span.offset, 19),
             },
             *milon_z
         );
@@ -612,4 +1288,28 @@ mod tests {
             ff4.speed_table[milon_z.speed_index as usize]
         );
     }
+
+    #[cfg_attr(feature = "ci_tests", ignore)]
+    #[test]
+    fn drop_table_rom_test() {
+        let map = RomMap::us();
+        let data = test_utils::load_rom().unwrap();
+        let ff4 = parse(&data, &map, &CharTable::us()).unwrap();
+
+        let goblin = ff4.resolved(0).unwrap();
+        assert_eq!("Goblin", goblin.name);
+        assert_eq!(5, goblin.monster.drop_rate);
+        assert_eq!(0x38, goblin.monster.drop_table_index);
+        // Goblins mostly drop Cure1.
+        assert_eq!(0xce, goblin.drop_table.common);
+        assert_eq!(
+            &data[map.monster_drop_table + 0x38 * 4..map.monster_drop_table + 0x39 * 4],
+            &[
+                goblin.drop_table.common,
+                goblin.drop_table.uncommon,
+                goblin.drop_table.rare,
+                goblin.drop_table.very_rare,
+            ][..]
+        );
+    }
 }
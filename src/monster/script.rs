@@ -1,9 +1,12 @@
 use nom::types::CompleteByteSlice;
 use nom::{ErrorKind, IResult, Needed, Slice};
 
-use std::error::Error;
+use super::super::error::from_nom;
+use super::super::span::{Span, Spanned};
+use super::super::Error;
+use super::{CreatureTypeSet, StatusSet, WeaknessSet};
 
-#[derive(Debug, PartialEq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub enum Action {
     // 0x00-0x30
     // 0x31-0x5e
@@ -21,7 +24,7 @@ pub enum Action {
     },
     // 0xe8
     ChangeCreatureType {
-        t: u8,
+        types: CreatureTypeSet,
     },
     // 0xe9
     ChangePhysicalAttackValue {
@@ -40,8 +43,9 @@ pub enum Action {
         data: u8,
     },
     // 0xed
+    // The argument is the first byte of a status field.
     SetElementalDefenses {
-        defenses: u8,
+        defenses: StatusSet,
     },
     // 0xee
     SetSpellPower {
@@ -49,7 +53,7 @@ pub enum Action {
     },
     // 0xef
     SetWeakness {
-        weakness: u8,
+        weaknesses: WeaknessSet,
     },
     // 0xf0
     SetSprite {
@@ -96,11 +100,140 @@ pub enum Action {
     StartChain,
     // 0xfe
     Wait,
+    // Opcodes no known action covers, with the argument bytes they take (see
+    // `unknown_arg_len`).  Kept so scripts using them still parse and encode
+    // back to the same bytes.
+    Unknown {
+        opcode: u8,
+        args: Vec<u8>,
+    },
+}
+
+// Argument bytes taken by opcodes without a known action.  0xf4 and 0xf5 keep
+// their one argument byte when it isn't a value the known actions accept.
+// The retail scripts never use 0xf6 or 0xfa (`parse_test` checks this);
+// they're read like the other 0xfX opcodes, with a single argument byte.
+// Everything else is covered by a known action or is the 0xff terminator.
+pub fn unknown_arg_len(opcode: u8) -> Option<usize> {
+    match opcode {
+        0xf4 | 0xf5 | 0xf6 | 0xfa => Some(1),
+        _ => None,
+    }
+}
+
+impl Action {
+    // Number of bytes the action takes up in a script.
+    pub fn size(&self) -> usize {
+        match self {
+            Action::Spell { .. }
+            | Action::EnemyAbility { .. }
+            | Action::PlayerCommand { .. }
+            | Action::ChainInto
+            | Action::EndChain
+            | Action::StartChain
+            | Action::Wait => 1,
+            Action::Unknown { args, .. } => 1 + args.len(),
+            _ => 2,
+        }
+    }
+
+    // Encodes the action, undoing the offsets and flag bits the parser
+    // strips.  Actions whose fields don't fit their encoding (an AoE spell
+    // past 0x2e, a flag value with the high bit set, ...) return a
+    // `BadScriptOpcode` for the opcode they would have produced.
+    pub fn encode(&self) -> Result<Vec<u8>, Error> {
+        let (opcode, arg) = match self {
+            Action::Spell { aoe: false, spell } => (*spell, None),
+            Action::Spell { aoe: true, spell } => (spell.wrapping_add(0x30), None),
+            Action::EnemyAbility { ability } => (*ability, None),
+            Action::PlayerCommand { command } => (command.wrapping_add(0xc0), None),
+            Action::ChangeCreatureType { types } => (0xe8, Some(types.to_bytes()[0])),
+            Action::ChangePhysicalAttackValue { index } => (0xe9, Some(*index)),
+            Action::ChangePhysicalDefenseValue { index } => (0xea, Some(*index)),
+            Action::ChangeMagicalDefenseValue { index } => (0xeb, Some(*index)),
+            Action::ModifySpeed { data } => (0xec, Some(*data)),
+            Action::SetElementalDefenses { defenses } => (0xed, Some(defenses.to_bytes()[0])),
+            Action::SetSpellPower { power } => (0xee, Some(*power)),
+            Action::SetWeakness { weaknesses } => (0xef, Some(weaknesses.to_bytes()[0])),
+            Action::SetSprite { index } => (0xf0, Some(*index)),
+            Action::ShowMessage {
+                suppress_next,
+                index,
+            } => (if *suppress_next { 0xf2 } else { 0xf1 }, Some(*index)),
+            Action::ChangeMusic { index } => (0xf3, Some(*index)),
+            Action::IncrementConditionFlag => (0xf4, Some(0x01)),
+            Action::SetConditionFlag { value } => (0xf4, Some(0x80 | value)),
+            Action::SetReaction { value } => (0xf5, Some(0x80 | value)),
+            Action::DarkenScreen { value } => (0xf7, Some(*value)),
+            Action::DebugDisplay { value } => (0xf8, Some(*value)),
+            Action::Target { value } => (0xf9, Some(*value)),
+            Action::ChainInto => (0xfb, None),
+            Action::EndChain => (0xfc, None),
+            Action::StartChain => (0xfd, None),
+            Action::Wait => (0xfe, None),
+            Action::Unknown { opcode, args } => {
+                let mut data = vec![*opcode];
+                data.extend_from_slice(args);
+                return self.check_encoding(data);
+            }
+        };
+        let mut data = vec![opcode];
+        data.extend(arg);
+        self.check_encoding(data)
+    }
+
+    fn check_encoding(&self, data: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let opcode = data[0];
+
+        // Anything which wouldn't parse back to the same action doesn't fit.
+        match parse_action(CompleteByteSlice(&data)) {
+            Ok((rest, ref action)) if rest.is_empty() && action == self => Ok(data),
+            _ => Err(Error::BadScriptOpcode {
+                table: "scripts",
+                offset: 0,
+                opcode,
+            }),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Serialize)]
 pub struct Script {
-    pub actions: Vec<Action>,
+    pub actions: Vec<Spanned<Action>>,
+    pub span: Span,
+}
+
+impl Script {
+    // Size including the 0xff terminator.
+    pub fn size(&self) -> usize {
+        self.actions.iter().map(|a| a.size()).sum::<usize>() + 1
+    }
+
+    // Sets the spans of the script and its actions for a script starting at
+    // `offset`.  Returns the offset just past the script.
+    pub fn locate(&mut self, offset: usize) -> usize {
+        self.span = Span::new(offset, self.size());
+        let mut offset = offset;
+        for action in &mut self.actions {
+            action.span = Span::new(offset, action.size());
+            offset = action.span.end();
+        }
+
+        self.span.end()
+    }
+
+    // Encodes the actions followed by the 0xff terminator.  Errors report
+    // offsets relative to the script.
+    pub fn encode(&self) -> Result<Vec<u8>, Error> {
+        let mut data = Vec::new();
+        for action in &self.actions {
+            let offset = data.len();
+            data.extend(action.encode().map_err(|e| e.relocate("scripts", offset))?);
+        }
+        data.push(0xff);
+
+        Ok(data)
+    }
 }
 
 macro_rules! ctag {
@@ -143,7 +276,7 @@ macro_rules! parse_range {
             res = Err(nom::Err::Error(nom::Context::Code($i, ErrorKind::Tag)))
         }
         res
-    };};
+    }};
 }
 
 named!(parse_single_spell<CompleteByteSlice, Action>,
@@ -191,6 +324,33 @@ fn parse_condition_flag(input: CompleteByteSlice) -> IResult<CompleteByteSlice,
     }
 }
 
+fn parse_unknown(input: CompleteByteSlice) -> IResult<CompleteByteSlice, Action> {
+    if input.is_empty() {
+        return nom::need_more(input, Needed::Size(1));
+    }
+    let opcode = input[0];
+    let len = match unknown_arg_len(opcode) {
+        Some(len) => len,
+        None => return Err(nom::Err::Error(nom::Context::Code(input, ErrorKind::Tag))),
+    };
+    if input.len() < 1 + len {
+        return nom::need_more(input, Needed::Size(1 + len));
+    }
+    // A terminator where an argument should be means the opcode doesn't
+    // take one after all, so the script can't be split reliably.
+    if input[1..1 + len].contains(&0xff) {
+        return Err(nom::Err::Failure(nom::Context::Code(input, ErrorKind::Tag)));
+    }
+
+    Ok((
+        input.slice(1 + len..),
+        Action::Unknown {
+            opcode,
+            args: input[1..1 + len].to_vec(),
+        },
+    ))
+}
+
 fn parse_set_reaction(input: CompleteByteSlice) -> IResult<CompleteByteSlice, Action> {
     if input.len() < 2 {
         return nom::need_more(input, Needed::Size(2));
@@ -215,14 +375,14 @@ named!(parse_action<CompleteByteSlice, Action>, alt!(
     parse_aoe_spell |
     parse_enemy_ability |
     parse_player_command |
-    parse_simple_action_arg!(0xe8, |v| Action::ChangeCreatureType{t: v}) |
+    parse_simple_action_arg!(0xe8, |v| Action::ChangeCreatureType{types: CreatureTypeSet::from_bytes([v])}) |
     parse_simple_action_arg!(0xe9, |v| Action::ChangePhysicalAttackValue{index: v}) |
     parse_simple_action_arg!(0xea, |v| Action::ChangePhysicalDefenseValue{index: v}) |
     parse_simple_action_arg!(0xeb, |v| Action::ChangeMagicalDefenseValue{index: v}) |
     parse_simple_action_arg!(0xec, |v| Action::ModifySpeed{data: v}) |
-    parse_simple_action_arg!(0xed, |v| Action::SetElementalDefenses{defenses: v}) |
+    parse_simple_action_arg!(0xed, |v| Action::SetElementalDefenses{defenses: StatusSet::from_bytes([v, 0, 0])}) |
     parse_simple_action_arg!(0xee, |v| Action::SetSpellPower{power: v}) |
-    parse_simple_action_arg!(0xef, |v| Action::SetWeakness{weakness: v}) |
+    parse_simple_action_arg!(0xef, |v| Action::SetWeakness{weaknesses: WeaknessSet::from_bytes([v])}) |
     parse_simple_action_arg!(0xf0, |v| Action::SetSprite{index: v}) |
     parse_simple_action_arg!(0xf1, |v| Action::ShowMessage{suppress_next: false, index: v}) |
     parse_simple_action_arg!(0xf2, |v| Action::ShowMessage{suppress_next: true, index: v}) |
@@ -236,27 +396,74 @@ named!(parse_action<CompleteByteSlice, Action>, alt!(
     parse_simple_action!(0xfb, Action::ChainInto) |
     parse_simple_action!(0xfc, Action::EndChain) |
     parse_simple_action!(0xfd, Action::StartChain) |
-    parse_simple_action!(0xfe, Action::Wait)
+    parse_simple_action!(0xfe, Action::Wait) |
+    parse_unknown
 ));
 
 named!(parse_script<CompleteByteSlice, Script>, do_parse!(
     a: many_till!(parse_action, ctag!(0xff)) >>
-    (Script{actions: a.0})
+    (Script{
+        actions: a.0.into_iter().map(|a| Spanned::new(Span::default(), a)).collect(),
+        span: Span::default(),
+    })
 ));
 
 named!(parse_scripts<CompleteByteSlice, Vec<Script>>,
      complete!(many_m_n!(0, 0x100, parse_script)));
 
-pub fn parse(data: &[u8]) -> Result<Vec<Script>, Box<Error>> {
-    Ok(parse_scripts(CompleteByteSlice(data))
-        .map_err(|x| format!("{}", x))?
-        .1)
+// Like `from_nom`, except that the parser stopping in front of a byte means
+// no action accepts that opcode there.
+fn script_error(e: nom::Err<CompleteByteSlice>, data: &[u8]) -> Error {
+    match e {
+        nom::Err::Error(nom::Context::Code(rest, _))
+        | nom::Err::Failure(nom::Context::Code(rest, _))
+            if !rest.is_empty() =>
+        {
+            Error::BadScriptOpcode {
+                table: "scripts",
+                offset: data.len() - rest.len(),
+                opcode: rest[0],
+            }
+        }
+        e => from_nom(e, data, "scripts", 0),
+    }
+}
+
+// Errors and spans report offsets relative to `data`.
+pub fn parse(data: &[u8]) -> Result<Vec<Script>, Error> {
+    let mut scripts = parse_scripts(CompleteByteSlice(data))
+        .map_err(|e| script_error(e, data))?
+        .1;
+    relocate(&mut scripts, 0);
+
+    Ok(scripts)
+}
+
+// Encodes `scripts` back to back.  This is the inverse of `parse`.  Errors
+// report offsets relative to the result.
+pub fn encode(scripts: &[Script]) -> Result<Vec<u8>, Error> {
+    let mut data = Vec::new();
+    for script in scripts {
+        let offset = data.len();
+        data.extend(script.encode().map_err(|e| e.relocate("scripts", offset))?);
+    }
+
+    Ok(data)
+}
+
+// Lays `scripts` out back to back starting at `offset`.
+pub fn relocate(scripts: &mut [Script], offset: usize) {
+    let mut offset = offset;
+    for script in scripts {
+        offset = script.locate(offset);
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::super::super::rom_map;
+    use super::super::super::rom_map::RomMap;
     use super::super::super::test_utils;
+    use super::super::{CreatureType, Status, Weakness};
     use super::*;
 
     #[test]
@@ -338,11 +545,11 @@ mod tests {
             parse_action(CompleteByteSlice(&[0xf4, 0xcf])).unwrap().1
         );
         assert_eq!(
-            Err(nom::Err::Error(nom::Context::Code(
-                CompleteByteSlice(&[244, 79]),
-                ErrorKind::Alt
-            ))),
-            parse_action(CompleteByteSlice(&[0xf4, 0x4f]))
+            Action::Unknown {
+                opcode: 0xf4,
+                args: vec![0x4f],
+            },
+            parse_action(CompleteByteSlice(&[0xf4, 0x4f])).unwrap().1
         );
     }
 
@@ -353,19 +560,21 @@ mod tests {
             parse_action(CompleteByteSlice(&[0xf5, 0xcf])).unwrap().1
         );
         assert_eq!(
-            Err(nom::Err::Error(nom::Context::Code(
-                CompleteByteSlice(&[245, 79]),
-                ErrorKind::Alt
-            ))),
-            parse_action(CompleteByteSlice(&[0xf5, 0x4f]))
+            Action::Unknown {
+                opcode: 0xf5,
+                args: vec![0x4f],
+            },
+            parse_action(CompleteByteSlice(&[0xf5, 0x4f])).unwrap().1
         );
     }
 
     #[test]
     fn parse_simple_commands_test() {
         assert_eq!(
-            Action::ChangeCreatureType { t: 0x0 },
-            parse_action(CompleteByteSlice(&[0xe8, 0x00])).unwrap().1
+            Action::ChangeCreatureType {
+                types: CreatureType::Undead.into()
+            },
+            parse_action(CompleteByteSlice(&[0xe8, 0x80])).unwrap().1
         );
         assert_eq!(
             Action::ChangePhysicalAttackValue { index: 0x1 },
@@ -384,16 +593,20 @@ mod tests {
             parse_action(CompleteByteSlice(&[0xec, 0x04])).unwrap().1
         );
         assert_eq!(
-            Action::SetElementalDefenses { defenses: 0x5 },
-            parse_action(CompleteByteSlice(&[0xed, 0x05])).unwrap().1
+            Action::SetElementalDefenses {
+                defenses: StatusSet::from(Status::AbsorbsElements) | Status::Fire.into()
+            },
+            parse_action(CompleteByteSlice(&[0xed, 0x41])).unwrap().1
         );
         assert_eq!(
             Action::SetSpellPower { power: 0x6 },
             parse_action(CompleteByteSlice(&[0xee, 0x06])).unwrap().1
         );
         assert_eq!(
-            Action::SetWeakness { weakness: 0x7 },
-            parse_action(CompleteByteSlice(&[0xef, 0x07])).unwrap().1
+            Action::SetWeakness {
+                weaknesses: Weakness::Ice.into()
+            },
+            parse_action(CompleteByteSlice(&[0xef, 0x02])).unwrap().1
         );
         assert_eq!(
             Action::SetSprite { index: 0x8 },
@@ -447,20 +660,29 @@ mod tests {
         );
     }
 
+    // A script as the nom parsers return it, before spans are filled in.
+    fn unlocated(actions: Vec<Action>) -> Script {
+        Script {
+            actions: actions
+                .into_iter()
+                .map(|a| Spanned::new(Span::default(), a))
+                .collect(),
+            span: Span::default(),
+        }
+    }
+
     #[test]
     fn parse_script_test() {
         assert_eq!(
-            Script {
-                actions: vec!(
-                    Action::EnemyAbility { ability: 111 },
-                    Action::Wait,
-                    Action::PlayerCommand { command: 0 },
-                    Action::Wait,
-                    Action::EnemyAbility { ability: 137 },
-                    Action::Wait,
-                    Action::PlayerCommand { command: 0 }
-                )
-            },
+            unlocated(vec!(
+                Action::EnemyAbility { ability: 111 },
+                Action::Wait,
+                Action::PlayerCommand { command: 0 },
+                Action::Wait,
+                Action::EnemyAbility { ability: 137 },
+                Action::Wait,
+                Action::PlayerCommand { command: 0 }
+            )),
             parse_script(CompleteByteSlice(&[
                 0x6f, 0xfe, 0xc0, 0xfe, 0x89, 0xfe, 0xc0, 0xff
             ]))
@@ -469,30 +691,57 @@ mod tests {
         );
     }
 
+    #[test]
+    fn size_test() {
+        let data = [0x6f, 0xfe, 0xf4, 0x81, 0xc0, 0xff];
+        let scripts = parse(&data).unwrap();
+        assert_eq!(data.len(), scripts[0].size());
+    }
+
+    #[test]
+    fn span_test() {
+        let data = [0xfe, 0xff, 0x6f, 0xf4, 0x81, 0xc0, 0xff];
+        let mut scripts = parse(&data).unwrap();
+        assert_eq!(Span::new(0, 2), scripts[0].span);
+        assert_eq!(Span::new(2, 5), scripts[1].span);
+        assert_eq!(
+            vec![Span::new(2, 1), Span::new(3, 2), Span::new(5, 1)],
+            scripts[1]
+                .actions
+                .iter()
+                .map(|a| a.span)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            Action::SetConditionFlag { value: 1 },
+            *scripts[1].actions[1]
+        );
+
+        relocate(&mut scripts, 0x76900);
+        assert_eq!(Span::new(0x76902, 5), scripts[1].span);
+        assert_eq!(Span::new(0x76903, 2), scripts[1].actions[1].span);
+    }
+
     #[test]
     fn parse_scripts_test() {
         assert_eq!(
             vec!(
-                Script {
-                    actions: vec!(
-                        Action::EnemyAbility { ability: 111 },
-                        Action::Wait,
-                        Action::PlayerCommand { command: 0 },
-                        Action::Wait,
-                        Action::EnemyAbility { ability: 137 },
-                        Action::Wait,
-                        Action::PlayerCommand { command: 0 }
-                    )
-                },
-                Script {
-                    actions: vec!(
-                        Action::PlayerCommand { command: 0 },
-                        Action::Wait,
-                        Action::PlayerCommand { command: 0 },
-                        Action::Wait,
-                        Action::EnemyAbility { ability: 152 }
-                    )
-                },
+                unlocated(vec!(
+                    Action::EnemyAbility { ability: 111 },
+                    Action::Wait,
+                    Action::PlayerCommand { command: 0 },
+                    Action::Wait,
+                    Action::EnemyAbility { ability: 137 },
+                    Action::Wait,
+                    Action::PlayerCommand { command: 0 }
+                )),
+                unlocated(vec!(
+                    Action::PlayerCommand { command: 0 },
+                    Action::Wait,
+                    Action::PlayerCommand { command: 0 },
+                    Action::Wait,
+                    Action::EnemyAbility { ability: 152 }
+                )),
             ),
             parse_scripts(CompleteByteSlice(&[
                 0x6f, 0xfe, 0xc0, 0xfe, 0x89, 0xfe, 0xc0, 0xff, 0xc0, 0xfe, 0xc0, 0xfe, 0x98, 0xff
@@ -502,14 +751,158 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_unknown_test() {
+        let data = [0xf6, 0x01, 0xfe, 0xfa, 0x02, 0xff, 0xf5, 0x00, 0xff];
+        let scripts = parse(&data).unwrap();
+        let actions = |script: &Script| {
+            script
+                .actions
+                .iter()
+                .map(|a| a.value.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(2, scripts.len());
+        assert_eq!(
+            vec![
+                Action::Unknown {
+                    opcode: 0xf6,
+                    args: vec![0x01],
+                },
+                Action::Wait,
+                Action::Unknown {
+                    opcode: 0xfa,
+                    args: vec![0x02],
+                },
+            ],
+            actions(&scripts[0])
+        );
+        assert_eq!(
+            vec![Action::Unknown {
+                opcode: 0xf5,
+                args: vec![0x00],
+            }],
+            actions(&scripts[1])
+        );
+        assert_eq!(Span::new(3, 2), scripts[0].actions[2].span);
+        assert_eq!(data.to_vec(), encode(&scripts).unwrap());
+
+        // The terminator is never an argument.
+        assert_eq!(
+            Err(Error::BadScriptOpcode {
+                table: "scripts",
+                offset: 3,
+                opcode: 0xfa,
+            }),
+            parse(&[0xf6, 0x01, 0xfe, 0xfa, 0xff, 0xff])
+        );
+        assert!(Action::Unknown {
+            opcode: 0xf6,
+            args: vec![0xff],
+        }
+        .encode()
+        .is_err());
+
+        // An unknown opcode cut off by the end of the data.
+        assert!(parse_action(CompleteByteSlice(&[0xf6])).is_err());
+
+        // Unknown actions which are really known ones don't encode.
+        assert!(Action::Unknown {
+            opcode: 0xf4,
+            args: vec![0x81],
+        }
+        .encode()
+        .is_err());
+        assert!(Action::Unknown {
+            opcode: 0xf6,
+            args: vec![],
+        }
+        .encode()
+        .is_err());
+    }
+
+    #[test]
+    fn encode_test() {
+        // One of every action.
+        let data = [
+            0x00, 0x30, 0x31, 0x5e, 0x5f, 0xbf, 0xc0, 0xe7, 0xe8, 0x80, 0xe9, 0x01, 0xea, 0x02,
+            0xeb, 0x03, 0xec, 0x04, 0xed, 0x41, 0xee, 0x06, 0xef, 0x02, 0xf0, 0x08, 0xf1, 0x09,
+            0xf2, 0x0a, 0xf3, 0x0b, 0xf4, 0x01, 0xf4, 0xcf, 0xf5, 0x80, 0xf7, 0x0c, 0xf8, 0x0d,
+            0xf9, 0x0e, 0xfb, 0xfc, 0xfd, 0xfe, 0xff, 0xfe, 0xff,
+        ];
+        let scripts = parse(&data).unwrap();
+        assert_eq!(2, scripts.len());
+        assert_eq!(data.to_vec(), encode(&scripts).unwrap());
+
+        assert_eq!(
+            vec![0x55],
+            Action::Spell {
+                aoe: true,
+                spell: 0x25,
+            }
+            .encode()
+            .unwrap()
+        );
+        assert_eq!(
+            Err(Error::BadScriptOpcode {
+                table: "scripts",
+                offset: 0,
+                opcode: 0x5f,
+            }),
+            Action::Spell {
+                aoe: true,
+                spell: 0x2f,
+            }
+            .encode()
+        );
+        assert_eq!(
+            Err(Error::BadScriptOpcode {
+                table: "scripts",
+                offset: 0,
+                opcode: 0xe8,
+            }),
+            Action::PlayerCommand { command: 0x28 }.encode()
+        );
+        assert_eq!(
+            Err(Error::BadScriptOpcode {
+                table: "scripts",
+                offset: 2,
+                opcode: 0xf5,
+            }),
+            encode(&[
+                unlocated(vec![Action::Wait]),
+                unlocated(vec![Action::SetReaction { value: 0x80 }]),
+            ])
+        );
+    }
+
     #[cfg_attr(feature = "ci_tests", ignore)]
     #[test]
     fn parse_test() {
         let data = test_utils::load_rom().unwrap();
-        parse(&data[rom_map::AI_EARTH_ATTACK_SCRIPTS_START..=rom_map::AI_EARTH_ATTACK_SCRIPTS_END])
-            .unwrap();
-        parse(&data[rom_map::AI_MOON_ATTACK_SCRIPTS_START..=rom_map::AI_MOON_ATTACK_SCRIPTS_END])
-            .unwrap();
+        let map = RomMap::us();
+        for region in &[map.earth_scripts(), map.moon_scripts()] {
+            let scripts = parse(region.slice(&data).unwrap()).unwrap();
+            // `unknown_arg_len` relies on 0xf6 and 0xfa never being used.
+            for script in &scripts {
+                for action in &script.actions {
+                    if let Action::Unknown { opcode, .. } = action.value {
+                        assert!(opcode != 0xf6 && opcode != 0xfa, "{}", region);
+                    }
+                }
+            }
+        }
     }
 
+    #[cfg_attr(feature = "ci_tests", ignore)]
+    #[test]
+    fn encode_rom_test() {
+        let data = test_utils::load_rom().unwrap();
+        let map = RomMap::us();
+        for region in &[map.earth_scripts(), map.moon_scripts()] {
+            let region_data = region.slice(&data).unwrap();
+            let encoded = encode(&parse(region_data).unwrap()).unwrap();
+            assert_eq!(&region_data[..encoded.len()], &encoded[..], "{}", region);
+        }
+    }
 }
@@ -0,0 +1,141 @@
+use std::fmt;
+use std::iter::StepBy;
+use std::ops::Range;
+
+use super::error::slice;
+use super::Error;
+
+// A named span of the ROM.  Regions carry an explicit length rather than an
+// end so there's no question of whether the end is inclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Region {
+    pub name: &'static str,
+    pub start: usize,
+    pub len: usize,
+}
+
+impl Region {
+    pub fn new(name: &'static str, start: usize, len: usize) -> Region {
+        Region { name, start, len }
+    }
+
+    // One past the last byte of the region.
+    pub fn end(&self) -> usize {
+        self.start + self.len
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end()
+    }
+
+    pub fn contains(&self, offset: usize) -> bool {
+        self.range().contains(&offset)
+    }
+
+    pub fn overlaps(&self, other: &Region) -> bool {
+        self.start < other.end() && other.start < self.end()
+    }
+
+    // Number of `stride` byte entries which start inside the region.
+    pub fn entries(&self, stride: usize) -> usize {
+        self.offsets(stride).count()
+    }
+
+    // Offset of each `stride` byte entry in the region.
+    pub fn offsets(&self, stride: usize) -> StepBy<Range<usize>> {
+        self.range().step_by(stride)
+    }
+
+    // Returns the bytes of the region or a `TruncatedRom` error naming it.
+    pub fn slice<'a>(&self, data: &'a [u8]) -> Result<&'a [u8], Error> {
+        slice(data, self.name, self.start, self.len)
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} [{:#x}, {:#x})", self.name, self.start, self.end())
+    }
+}
+
+// Returns every pair of regions which share a byte.
+pub fn overlaps(regions: &[Region]) -> Vec<(Region, Region)> {
+    let mut sorted = regions.to_vec();
+    sorted.sort_by_key(|r| (r.start, r.len));
+
+    let mut overlaps = Vec::new();
+    for (i, a) in sorted.iter().enumerate() {
+        for b in sorted[i + 1..].iter().take_while(|b| b.start < a.end()) {
+            if a.overlaps(b) {
+                overlaps.push((*a, *b));
+            }
+        }
+    }
+
+    overlaps
+}
+
+// Returns the unmapped spans between the first and last region.
+pub fn gaps(regions: &[Region]) -> Vec<Region> {
+    let mut sorted = regions.to_vec();
+    sorted.sort_by_key(|r| r.start);
+
+    let mut gaps = Vec::new();
+    let mut end = match sorted.first() {
+        Some(r) => r.start,
+        None => return gaps,
+    };
+    for r in &sorted {
+        if r.start > end {
+            gaps.push(Region::new("unmapped", end, r.start - end));
+        }
+        end = end.max(r.end());
+    }
+
+    gaps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn region_test() {
+        let r = Region::new("test", 0x10, 0x8);
+        assert_eq!(0x18, r.end());
+        assert!(r.contains(0x10));
+        assert!(r.contains(0x17));
+        assert!(!r.contains(0x18));
+        assert_eq!(3, r.entries(3));
+        assert_eq!(vec![0x10, 0x13, 0x16], r.offsets(3).collect::<Vec<_>>());
+        assert_eq!("test [0x10, 0x18)", format!("{}", r));
+
+        let data = [0u8; 0x20];
+        assert_eq!(Ok(&data[0x10..0x18]), r.slice(&data));
+        assert_eq!(
+            Err(Error::TruncatedRom {
+                table: "test",
+                offset: 0x10,
+                len: 0x8,
+            }),
+            r.slice(&data[..0x17])
+        );
+    }
+
+    #[test]
+    fn overlaps_test() {
+        let a = Region::new("a", 0x00, 0x10);
+        let b = Region::new("b", 0x10, 0x10);
+        let c = Region::new("c", 0x18, 0x10);
+        let d = Region::new("d", 0x40, 0x10);
+        assert!(!a.overlaps(&b));
+        assert!(b.overlaps(&c));
+        assert_eq!(vec![(b, c)], overlaps(&[d, c, b, a]));
+
+        assert_eq!(
+            vec![Region::new("unmapped", 0x28, 0x18)],
+            gaps(&[d, c, b, a])
+        );
+        assert!(gaps(&[]).is_empty());
+    }
+}
@@ -0,0 +1,77 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Copier devices prepend a 512 byte header to the ROM dump.
+pub const COPIER_HEADER_LEN: usize = 0x200;
+
+// A ROM image with any copier header stripped so `rom_map` offsets can be
+// used directly on `data`.  The header is kept so it can be restored when
+// the image is written back out.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rom {
+    pub data: Vec<u8>,
+    pub header: Option<Vec<u8>>,
+}
+
+impl Rom {
+    // ROM sizes are multiples of 1KiB so a file with 512 extra bytes has a
+    // copier header.
+    pub fn from_bytes(mut data: Vec<u8>) -> Rom {
+        let header = if data.len() % 0x400 == COPIER_HEADER_LEN {
+            Some(data.drain(..COPIER_HEADER_LEN).collect())
+        } else {
+            None
+        };
+
+        Rom { data, header }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Rom> {
+        Ok(Rom::from_bytes(fs::read(path)?))
+    }
+
+    pub fn has_header(&self) -> bool {
+        self.header.is_some()
+    }
+
+    // Returns the file contents, including the copier header if the ROM was
+    // loaded with one.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        if let Some(header) = &self.header {
+            bytes.extend_from_slice(header);
+        }
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headerless_test() {
+        let rom = Rom::from_bytes(vec![0x55; 0x80000]);
+        assert!(!rom.has_header());
+        assert_eq!(0x80000, rom.data.len());
+        assert_eq!(vec![0x55; 0x80000], rom.to_bytes());
+    }
+
+    #[test]
+    fn header_test() {
+        let mut data = vec![0xaa; COPIER_HEADER_LEN];
+        data.extend_from_slice(&[0x55; 0x80000]);
+
+        let rom = Rom::from_bytes(data.clone());
+        assert!(rom.has_header());
+        assert_eq!(vec![0x55; 0x80000], rom.data);
+        assert_eq!(Some(vec![0xaa; COPIER_HEADER_LEN]), rom.header);
+        assert_eq!(data, rom.to_bytes());
+    }
+}
@@ -0,0 +1,251 @@
+use super::error::slice;
+use super::Error;
+
+// The LoROM internal header lives at the end of the first bank.
+pub const HEADER_START: usize = 0x7fc0;
+pub const HEADER_LEN: usize = 0x20;
+
+const TITLE_LEN: usize = 21;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum Release {
+    Ff2UsV10,
+    Ff2UsV11,
+    Ff4Jp,
+    Ff4JpEasyType,
+}
+
+impl Release {
+    pub fn name(self) -> &'static str {
+        match self {
+            Release::Ff2UsV10 => "Final Fantasy II (US) v1.0",
+            Release::Ff2UsV11 => "Final Fantasy II (US) v1.1",
+            Release::Ff4Jp => "Final Fantasy IV (JP)",
+            Release::Ff4JpEasyType => "Final Fantasy IV Easytype (JP)",
+        }
+    }
+}
+
+// CRC32s of unmodified, headerless dumps.  Both revisions of the Japanese
+// release map to `Ff4Jp`.  The Easytype CRC32 isn't listed yet, so Easytype
+// images aren't identified.
+const KNOWN_CRC32S: [(u32, Release); 4] = [
+    (0x65d0_a825, Release::Ff2UsV10),
+    (0x2308_4fcd, Release::Ff2UsV11),
+    (0x2102_7c5d, Release::Ff4Jp),
+    (0xcaa1_5e97, Release::Ff4Jp),
+];
+
+// Internal title of the US release.  Its two versions differ only in the
+// version byte.
+const US_TITLE: &str = "FINAL FANTASY II";
+const US_REGION: u8 = 0x01;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RomInfo {
+    pub title: String,
+    pub map_mode: u8,
+    pub rom_type: u8,
+    // Size in bytes as declared by the header.
+    pub rom_size: usize,
+    pub region: u8,
+    pub version: u8,
+    pub checksum_complement: u16,
+    pub checksum: u16,
+    // Values computed over the whole image.
+    pub computed_checksum: u16,
+    pub crc32: u32,
+}
+
+impl RomInfo {
+    // Parses the internal header of a ROM with any copier header stripped.
+    pub fn parse(data: &[u8]) -> Result<RomInfo, Error> {
+        let header = slice(data, "internal header", HEADER_START, HEADER_LEN)?;
+        let title = String::from_utf8_lossy(&header[..TITLE_LEN])
+            .trim_end()
+            .to_string();
+
+        Ok(RomInfo {
+            title,
+            map_mode: header[0x15],
+            rom_type: header[0x16],
+            rom_size: 0x400 << (header[0x17] & 0xf),
+            region: header[0x19],
+            version: header[0x1b],
+            checksum_complement: u16::from(header[0x1c]) | (u16::from(header[0x1d]) << 8),
+            checksum: u16::from(header[0x1e]) | (u16::from(header[0x1f]) << 8),
+            computed_checksum: snes_checksum(data),
+            crc32: crc32(data),
+        })
+    }
+
+    pub fn is_lorom(&self) -> bool {
+        self.map_mode & 0x01 == 0
+    }
+
+    pub fn checksum_valid(&self) -> bool {
+        self.checksum ^ self.checksum_complement == 0xffff
+            && self.checksum == self.computed_checksum
+    }
+
+    // True if the image is a byte for byte copy of a known release.
+    pub fn is_unmodified(&self) -> bool {
+        KNOWN_CRC32S.iter().any(|&(crc, _)| crc == self.crc32)
+    }
+
+    // Identifies the release by CRC32.  Images of the US release which don't
+    // match a known CRC32 (hacks, patched dumps) are recognized by their exact
+    // internal title, region and version.
+    pub fn release(&self) -> Option<Release> {
+        if let Some(&(_, release)) = KNOWN_CRC32S.iter().find(|&&(crc, _)| crc == self.crc32) {
+            return Some(release);
+        }
+
+        if self.title != US_TITLE || self.region != US_REGION {
+            return None;
+        }
+        match self.version {
+            0x00 => Some(Release::Ff2UsV10),
+            0x01 => Some(Release::Ff2UsV11),
+            _ => None,
+        }
+    }
+}
+
+// Sums every byte of the image.  Images whose size isn't a power of two have
+// their last part mirrored up to the next power of two like the hardware.
+fn snes_checksum(data: &[u8]) -> u16 {
+    let sum = |d: &[u8]| {
+        d.iter()
+            .fold(0u32, |acc, &b| acc.wrapping_add(u32::from(b)))
+    };
+    if data.is_empty() {
+        return 0;
+    }
+
+    let mut base = 1;
+    while base * 2 <= data.len() {
+        base *= 2;
+    }
+    let (first, rest) = data.split_at(base);
+    let mut total = sum(first);
+    if !rest.is_empty() {
+        let repeat = base / rest.len();
+        total = total.wrapping_add(sum(rest).wrapping_mul(repeat as u32));
+    }
+
+    total as u16
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &b in data {
+        crc ^= u32::from(b);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_utils;
+    use super::*;
+
+    fn synthetic_rom(title: &str, region: u8, version: u8) -> Vec<u8> {
+        let mut data = vec![0; 0x8000];
+        let header = &mut data[HEADER_START..HEADER_START + HEADER_LEN];
+        for (i, b) in header[..TITLE_LEN].iter_mut().enumerate() {
+            *b = *title.as_bytes().get(i).unwrap_or(&b' ');
+        }
+        header[0x15] = 0x20;
+        header[0x17] = 0x0a;
+        header[0x19] = region;
+        header[0x1b] = version;
+
+        // The checksum and complement always sum to 0x1fe.
+        let checksum = snes_checksum(&data).wrapping_add(0x1fe);
+        data[HEADER_START + 0x1c] = !checksum as u8;
+        data[HEADER_START + 0x1d] = (!checksum >> 8) as u8;
+        data[HEADER_START + 0x1e] = checksum as u8;
+        data[HEADER_START + 0x1f] = (checksum >> 8) as u8;
+        data
+    }
+
+    #[test]
+    fn crc32_test() {
+        assert_eq!(0xcbf4_3926, crc32(b"123456789"));
+    }
+
+    #[test]
+    fn snes_checksum_test() {
+        // The last byte is mirrored to fill out four bytes.
+        assert_eq!(0x0009, snes_checksum(&[1, 2, 3]));
+        assert_eq!(0x03fc, snes_checksum(&[0xff; 3]));
+    }
+
+    #[test]
+    fn parse_test() {
+        let info = RomInfo::parse(&synthetic_rom("FINAL FANTASY II", 0x01, 0x01)).unwrap();
+        assert_eq!("FINAL FANTASY II", info.title);
+        assert!(info.is_lorom());
+        assert_eq!(0x100000, info.rom_size);
+        assert_eq!(1, info.version);
+        assert!(info.checksum_valid());
+        assert!(!info.is_unmodified());
+        assert_eq!(Some(Release::Ff2UsV11), info.release());
+
+        // Other releases are only identified by CRC32, and other Final Fantasy
+        // titles aren't mistaken for the US release.
+        for &(title, region, version) in &[
+            ("FINAL FANTASY 4", 0x00, 0x00),
+            ("FINAL FANTASY III", 0x01, 0x00),
+            ("FF MYSTIC QUEST", 0x01, 0x00),
+            ("FINAL FANTASY II", 0x00, 0x00),
+            ("FINAL FANTASY II", 0x01, 0x02),
+            ("SUPER MARIOWORLD", 0x01, 0x00),
+        ] {
+            let info = RomInfo::parse(&synthetic_rom(title, region, version)).unwrap();
+            assert_eq!(None, info.release(), "{}", title);
+        }
+
+        assert_eq!(
+            Err(Error::TruncatedRom {
+                table: "internal header",
+                offset: HEADER_START,
+                len: HEADER_LEN,
+            }),
+            RomInfo::parse(&[0; 0x100])
+        );
+    }
+
+    #[test]
+    fn known_crc32_test() {
+        // Each known dump maps to its release whatever its header says.
+        let expected = [
+            (0x65d0_a825, Release::Ff2UsV10),
+            (0x2308_4fcd, Release::Ff2UsV11),
+            (0x2102_7c5d, Release::Ff4Jp),
+            (0xcaa1_5e97, Release::Ff4Jp),
+        ];
+        assert_eq!(KNOWN_CRC32S, expected);
+        for &(crc, release) in &expected {
+            let mut info = RomInfo::parse(&synthetic_rom("FINAL FANTASY II", 0x01, 0x00)).unwrap();
+            info.crc32 = crc;
+            assert!(info.is_unmodified());
+            assert_eq!(Some(release), info.release(), "{:#010x}", crc);
+        }
+    }
+
+    #[cfg_attr(feature = "ci_tests", ignore)]
+    #[test]
+    fn parse_rom_test() {
+        let data = test_utils::load_rom().unwrap();
+        let info = RomInfo::parse(&data).unwrap();
+
+        assert!(info.checksum_valid());
+        assert!(info.release().is_some());
+    }
+}
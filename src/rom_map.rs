@@ -1,37 +1,417 @@
-pub const MONSTER_NAME_TABLE: usize = 0x71800;
-pub const MONSTER_NAME_TABLE_END: usize = 0x71f00;
+use std::fs::File;
+use std::io;
+use std::path::Path;
 
-pub const MONSTER_GP_TABLE: usize = 0x72000;
-pub const MONSTER_GP_TABLE_ENTRIES: usize = 0xe0;
+use super::region::{self, Region};
+use super::rom_info::Release;
+use super::Error;
 
-pub const MONSTER_XP_TABLE: usize = 0x721C0;
-pub const MONSTER_XP_TABLE_ENTRIES: usize = 0xe0;
+// Where each table lives in a particular release.  Offsets are into the ROM
+// image with any copier header stripped (see `rom::Rom`) and every table has
+// an explicit length in bytes.  Parsers get at the tables through the
+// `Region` accessors below.
+//
+// Built in maps cover the retail releases.  ROM hacks which moved tables can
+// describe their layout in a JSON file with the same field names (see
+// `RomMap::load`).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RomMap {
+    pub monster_name_table: usize,
+    pub monster_name_table_len: usize,
 
-pub const MONSTER_OFFSET_TABLE: usize = 0x726A0;
-pub const MONSTER_OFFSET_TABLE_ENTRIES: usize = 0xe0;
+    pub monster_gp_table: usize,
+    pub monster_gp_table_len: usize,
 
-pub const MONSTER_STAT_TABLE: usize = 0x72380;
-pub const MONSTER_STAT_TABLE_END: usize = 0x7261f;
+    pub monster_xp_table: usize,
+    pub monster_xp_table_len: usize,
 
-pub const MONSTER_SPEED_TABLE: usize = 0x72620;
-pub const MONSTER_SPEED_TABLE_END: usize = 0x7269f;
+    pub monster_offset_table: usize,
+    pub monster_offset_table_len: usize,
+    // Entries in the monster offset table are 16 bit pointers into this bank.
+    pub monster_info_bank: u8,
 
-pub const MONSTER_DROP_TABLE: usize = 0x71e00;
-pub const MONSTER_DROP_TABLE_END: usize = 0x71f00;
+    // The variable length records the offset table points at.  This is the
+    // space `MonsterData::write_to` packs records into.
+    pub monster_record_table: usize,
+    pub monster_record_table_len: usize,
 
-pub const MONSTER_INFO_OFFSET: usize = 0x72860 - 0xa860;
+    pub monster_stat_table: usize,
+    pub monster_stat_table_len: usize,
 
-pub const ATTACK_GROUP_START: usize = 0x76030;
-pub const ATTACK_GROUP_END: usize = 0x765FF;
+    pub monster_speed_table: usize,
+    pub monster_speed_table_len: usize,
 
-pub const AI_CONDITION_SET_TABLE_START: usize = 0x76600;
-pub const AI_CONDITION_SET_TABLE_END: usize = 0x76700;
+    pub monster_drop_table: usize,
+    pub monster_drop_table_len: usize,
 
-pub const AI_CONDITION_TABLE_START: usize = 0x76700;
-pub const AI_CONDITION_TABLE_END: usize = 0x76900;
+    pub attack_group_table: usize,
+    pub attack_group_table_len: usize,
 
-pub const AI_EARTH_ATTACK_SCRIPTS_START: usize = 0x76900;
-pub const AI_EARTH_ATTACK_SCRIPTS_END: usize = 0x771ff;
+    pub ai_condition_set_table: usize,
+    pub ai_condition_set_table_len: usize,
 
-pub const AI_MOON_ATTACK_SCRIPTS_START: usize = 0x736c0;
-pub const AI_MOON_ATTACK_SCRIPTS_END: usize = 0x73acf;
+    pub ai_condition_table: usize,
+    pub ai_condition_table_len: usize,
+
+    pub ai_earth_attack_scripts: usize,
+    pub ai_earth_attack_scripts_len: usize,
+
+    pub ai_moon_attack_scripts: usize,
+    pub ai_moon_attack_scripts_len: usize,
+
+    pub battle_message_pointers: usize,
+    pub battle_message_pointers_len: usize,
+    // Message pointers are 16 bit pointers into this bank.
+    pub battle_message_bank: u8,
+    pub battle_message_text: usize,
+    pub battle_message_text_len: usize,
+
+    // Each dialogue bank is a table of 16 bit pointers into `_bank` followed
+    // by the text they point at.
+    pub dialogue_bank1_pointers: usize,
+    pub dialogue_bank1_pointers_len: usize,
+    pub dialogue_bank1_bank: u8,
+    pub dialogue_bank1_text: usize,
+    pub dialogue_bank1_text_len: usize,
+
+    pub dialogue_bank2_pointers: usize,
+    pub dialogue_bank2_pointers_len: usize,
+    pub dialogue_bank2_bank: u8,
+    pub dialogue_bank2_text: usize,
+    pub dialogue_bank2_text_len: usize,
+}
+
+impl RomMap {
+    // Final Fantasy II (US).  v1.0 and v1.1 share a layout.
+    pub fn us() -> RomMap {
+        RomMap {
+            monster_name_table: 0x71800,
+            monster_name_table_len: 0xe0 * 8,
+
+            monster_gp_table: 0x72000,
+            monster_gp_table_len: 0xe0 * 2,
+
+            monster_xp_table: 0x721c0,
+            monster_xp_table_len: 0xe0 * 2,
+
+            monster_offset_table: 0x726a0,
+            monster_offset_table_len: 0xe0 * 2,
+            monster_info_bank: 0x0e,
+
+            // Runs from the end of the offset table up to the moon scripts.
+            monster_record_table: 0x72860,
+            monster_record_table_len: 0x736c0 - 0x72860,
+
+            monster_stat_table: 0x72380,
+            monster_stat_table_len: 0xe0 * 3,
+
+            monster_speed_table: 0x72620,
+            monster_speed_table_len: 0x40 * 2,
+
+            // Sits between the names and the gp table.
+            monster_drop_table: 0x71f00,
+            monster_drop_table_len: 0x40 * 4,
+
+            attack_group_table: 0x76030,
+            attack_group_table_len: 0x5d0,
+
+            ai_condition_set_table: 0x76600,
+            ai_condition_set_table_len: 0x100,
+
+            ai_condition_table: 0x76700,
+            ai_condition_table_len: 0x200,
+
+            ai_earth_attack_scripts: 0x76900,
+            ai_earth_attack_scripts_len: 0x900,
+
+            ai_moon_attack_scripts: 0x736c0,
+            ai_moon_attack_scripts_len: 0x410,
+
+            battle_message_pointers: 0x78000,
+            battle_message_pointers_len: 0x100 * 2,
+            battle_message_bank: 0x0f,
+            battle_message_text: 0x78200,
+            battle_message_text_len: 0x1e00,
+
+            dialogue_bank1_pointers: 0x80000,
+            dialogue_bank1_pointers_len: 0x100 * 2,
+            dialogue_bank1_bank: 0x10,
+            dialogue_bank1_text: 0x80200,
+            dialogue_bank1_text_len: 0x7e00,
+
+            dialogue_bank2_pointers: 0x88000,
+            dialogue_bank2_pointers_len: 0x100 * 2,
+            dialogue_bank2_bank: 0x11,
+            dialogue_bank2_text: 0x88200,
+            dialogue_bank2_text_len: 0x7e00,
+        }
+    }
+
+    // The map for `release`, if it's been worked out.  The Japanese releases
+    // aren't mapped yet: their offsets need checking against a JP dump before
+    // they're added here.  Until then a JP image can only be parsed with a
+    // map passed to `parse_rom_with_map`.
+    pub fn for_release(release: Release) -> Option<RomMap> {
+        match release {
+            Release::Ff2UsV10 | Release::Ff2UsV11 => Some(RomMap::us()),
+            Release::Ff4Jp | Release::Ff4JpEasyType => None,
+        }
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<RomMap> {
+        serde_json::from_str(json)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<RomMap> {
+        Ok(serde_json::from_reader(io::BufReader::new(File::open(
+            path,
+        )?))?)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn monster_names(&self) -> Region {
+        Region::new(
+            "monster names",
+            self.monster_name_table,
+            self.monster_name_table_len,
+        )
+    }
+
+    pub fn monster_gp(&self) -> Region {
+        Region::new(
+            "monster gp",
+            self.monster_gp_table,
+            self.monster_gp_table_len,
+        )
+    }
+
+    pub fn monster_xp(&self) -> Region {
+        Region::new(
+            "monster xp",
+            self.monster_xp_table,
+            self.monster_xp_table_len,
+        )
+    }
+
+    pub fn monster_offsets(&self) -> Region {
+        Region::new(
+            "monster offsets",
+            self.monster_offset_table,
+            self.monster_offset_table_len,
+        )
+    }
+
+    pub fn monster_records(&self) -> Region {
+        Region::new(
+            "monster records",
+            self.monster_record_table,
+            self.monster_record_table_len,
+        )
+    }
+
+    pub fn monster_stats(&self) -> Region {
+        Region::new(
+            "monster stats",
+            self.monster_stat_table,
+            self.monster_stat_table_len,
+        )
+    }
+
+    pub fn monster_speeds(&self) -> Region {
+        Region::new(
+            "monster speeds",
+            self.monster_speed_table,
+            self.monster_speed_table_len,
+        )
+    }
+
+    pub fn monster_drops(&self) -> Region {
+        Region::new(
+            "monster drops",
+            self.monster_drop_table,
+            self.monster_drop_table_len,
+        )
+    }
+
+    pub fn attack_groups(&self) -> Region {
+        Region::new(
+            "attack groups",
+            self.attack_group_table,
+            self.attack_group_table_len,
+        )
+    }
+
+    pub fn condition_sets(&self) -> Region {
+        Region::new(
+            "condition sets",
+            self.ai_condition_set_table,
+            self.ai_condition_set_table_len,
+        )
+    }
+
+    pub fn conditions(&self) -> Region {
+        Region::new(
+            "conditions",
+            self.ai_condition_table,
+            self.ai_condition_table_len,
+        )
+    }
+
+    pub fn earth_scripts(&self) -> Region {
+        Region::new(
+            "earth scripts",
+            self.ai_earth_attack_scripts,
+            self.ai_earth_attack_scripts_len,
+        )
+    }
+
+    pub fn moon_scripts(&self) -> Region {
+        Region::new(
+            "moon scripts",
+            self.ai_moon_attack_scripts,
+            self.ai_moon_attack_scripts_len,
+        )
+    }
+
+    pub fn battle_message_pointers(&self) -> Region {
+        Region::new(
+            "battle message pointers",
+            self.battle_message_pointers,
+            self.battle_message_pointers_len,
+        )
+    }
+
+    pub fn battle_messages(&self) -> Region {
+        Region::new(
+            "battle messages",
+            self.battle_message_text,
+            self.battle_message_text_len,
+        )
+    }
+
+    pub fn dialogue_bank1_pointers(&self) -> Region {
+        Region::new(
+            "dialogue bank 1 pointers",
+            self.dialogue_bank1_pointers,
+            self.dialogue_bank1_pointers_len,
+        )
+    }
+
+    pub fn dialogue_bank1(&self) -> Region {
+        Region::new(
+            "dialogue bank 1",
+            self.dialogue_bank1_text,
+            self.dialogue_bank1_text_len,
+        )
+    }
+
+    pub fn dialogue_bank2_pointers(&self) -> Region {
+        Region::new(
+            "dialogue bank 2 pointers",
+            self.dialogue_bank2_pointers,
+            self.dialogue_bank2_pointers_len,
+        )
+    }
+
+    pub fn dialogue_bank2(&self) -> Region {
+        Region::new(
+            "dialogue bank 2",
+            self.dialogue_bank2_text,
+            self.dialogue_bank2_text_len,
+        )
+    }
+
+    // Every fixed region in the map sorted by start.
+    pub fn regions(&self) -> Vec<Region> {
+        let mut regions = vec![
+            self.monster_names(),
+            self.monster_gp(),
+            self.monster_xp(),
+            self.monster_offsets(),
+            self.monster_records(),
+            self.monster_stats(),
+            self.monster_speeds(),
+            self.monster_drops(),
+            self.attack_groups(),
+            self.condition_sets(),
+            self.conditions(),
+            self.earth_scripts(),
+            self.moon_scripts(),
+            self.battle_message_pointers(),
+            self.battle_messages(),
+            self.dialogue_bank1_pointers(),
+            self.dialogue_bank1(),
+            self.dialogue_bank2_pointers(),
+            self.dialogue_bank2(),
+        ];
+        regions.sort_by_key(|r| r.start);
+        regions
+    }
+
+    // Unmapped spans between the mapped regions.
+    pub fn gaps(&self) -> Vec<Region> {
+        region::gaps(&self.regions())
+    }
+
+    // Refuses maps where two tables share bytes since writing one would
+    // corrupt the other.
+    pub fn validate(&self) -> Result<(), Error> {
+        match region::overlaps(&self.regions()).first() {
+            Some((a, b)) => Err(Error::RegionOverlap {
+                table: b.name,
+                offset: b.start,
+                other: a.name,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_test() {
+        let map = RomMap::us();
+        assert_eq!(map, RomMap::from_json(&map.to_json()).unwrap());
+
+        // Misspelled fields shouldn't silently fall back to anything.
+        let json = map.to_json().replace("monster_gp_table\"", "monster_gp\"");
+        assert!(RomMap::from_json(&json).is_err());
+    }
+
+    #[test]
+    fn validate_test() {
+        let map = RomMap::us();
+        assert_eq!(Ok(()), map.validate());
+        assert_eq!(
+            vec![(0x73ad0, 0x76030), (0x77200, 0x78000), (0x7a000, 0x80000)],
+            map.gaps()
+                .iter()
+                .map(|r| (r.start, r.end()))
+                .collect::<Vec<_>>()
+        );
+
+        let mut map = RomMap::us();
+        map.monster_drop_table = 0x71e00;
+        assert_eq!(
+            Err(Error::RegionOverlap {
+                table: "monster drops",
+                offset: 0x71e00,
+                other: "monster names",
+            }),
+            map.validate()
+        );
+    }
+
+    #[test]
+    fn for_release_test() {
+        assert_eq!(Some(RomMap::us()), RomMap::for_release(Release::Ff2UsV11));
+        assert_eq!(None, RomMap::for_release(Release::Ff4Jp));
+    }
+}
@@ -0,0 +1,70 @@
+use std::ops::{Deref, DerefMut, Range};
+
+// Where a parsed value came from: the absolute ROM offset of its first byte
+// and its length in bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Span {
+    pub offset: usize,
+    pub len: usize,
+}
+
+impl Span {
+    pub fn new(offset: usize, len: usize) -> Span {
+        Span { offset, len }
+    }
+
+    pub fn end(&self) -> usize {
+        self.offset + self.len
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.end()
+    }
+}
+
+// A value along with the span it was parsed from.  Derefs to the value so
+// it can mostly be used in its place.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Spanned<T> {
+    pub span: Span,
+    pub value: T,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(span: Span, value: T) -> Spanned<T> {
+        Spanned { span, value }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Deref for Spanned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for Spanned<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spanned_test() {
+        let mut s = Spanned::new(Span::new(0x10, 2), vec![1u8, 2]);
+        assert_eq!(0x12, s.span.end());
+        assert_eq!(0x10..0x12, s.span.range());
+        assert_eq!(2, s.len());
+        s.push(3);
+        assert_eq!(vec![1, 2, 3], s.into_inner());
+    }
+}
@@ -1,5 +1,10 @@
 use std::collections::HashMap;
-use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::rom_info::Release;
+use super::Error;
 
 lazy_static! {
     static ref SPECIAL_CHARS: HashMap<u8, &'static str> = {
@@ -143,24 +148,485 @@ lazy_static! {
     };
 }
 
-pub fn decode(data: &[u8]) -> Result<String, Box<Error>> {
-    let mut s = String::from("");
+// The text `b` decodes to in the US release, if it has any.
+fn decode_byte(b: u8) -> Option<String> {
+    if (0x42..=0x5b).contains(&b) {
+        Some(((b - 0x42 + b'A') as char).to_string())
+    } else if (0x5c..=0x75).contains(&b) {
+        Some(((b - 0x5c + b'a') as char).to_string())
+    } else if (0x80..=0x89).contains(&b) {
+        Some(((b - 0x80 + b'0') as char).to_string())
+    } else {
+        SPECIAL_CHARS.get(&b).map(|s| s.to_string())
+    }
+}
+
+// Japanese fonts write voiced kana as the plain kana followed by a dakuten
+// or handakuten mark.
+const DAKUTEN: char = '゛';
+const HANDAKUTEN: char = '゜';
+
+lazy_static! {
+    static ref US: CharTable = CharTable::us();
+
+    // Voiced kana keyed by (kana, mark).
+    static ref VOICED: HashMap<(char, char), char> = {
+        let mut map = HashMap::new();
+        let voiceable = "かきくけこさしすせそたちつてとはひふへほカキクケコサシスセソタチツテトハヒフヘホ";
+        for kana in voiceable.chars() {
+            map.insert((kana, DAKUTEN), char::from_u32(kana as u32 + 1).unwrap());
+        }
+        for kana in "はひふへほハヒフヘホ".chars() {
+            map.insert((kana, HANDAKUTEN), char::from_u32(kana as u32 + 2).unwrap());
+        }
+        map.insert(('う', DAKUTEN), 'ゔ');
+        map.insert(('ウ', DAKUTEN), 'ヴ');
+        map
+    };
+
+    static ref UNVOICED: HashMap<char, (char, char)> =
+        VOICED.iter().map(|(&k, &v)| (v, k)).collect();
+}
+
+// A mapping between byte sequences and text.  Most entries are a single byte
+// but `.tbl` files can also map longer sequences, which decoding prefers over
+// their prefixes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CharTable {
+    // Sorted by bytes so encoding is deterministic and exports are stable.
+    entries: Vec<(Vec<u8>, String)>,
+    index: HashMap<Vec<u8>, usize>,
+    // The entry `encode_lossless` picks for each single character or escape.
+    canonical: HashMap<String, usize>,
+    max_key_len: usize,
+    // Bytes which always start a two byte code, even one with no entry.
+    pages: Vec<u8>,
+}
+
+impl CharTable {
+    // Later entries for the same bytes replace earlier ones.  Entries with no
+    // bytes are dropped.
+    pub fn new(entries: Vec<(Vec<u8>, String)>) -> CharTable {
+        let mut map: HashMap<Vec<u8>, String> = HashMap::new();
+        for (bytes, text) in entries {
+            if !bytes.is_empty() {
+                map.insert(bytes, text);
+            }
+        }
+        let mut entries: Vec<(Vec<u8>, String)> = map.into_iter().collect();
+        entries.sort();
+
+        let index: HashMap<Vec<u8>, usize> = entries
+            .iter()
+            .enumerate()
+            .map(|(i, (bytes, _))| (bytes.clone(), i))
+            .collect();
+        let mut canonical: HashMap<String, usize> = HashMap::new();
+        for (i, (bytes, text)) in entries.iter().enumerate() {
+            if !is_single(text) {
+                continue;
+            }
+            // Shortest encoding wins, then the lowest bytes.
+            let entry = canonical.entry(text.clone()).or_insert(i);
+            if bytes.len() < entries[*entry].0.len() {
+                *entry = i;
+            }
+        }
+        let max_key_len = entries.iter().map(|(b, _)| b.len()).max().unwrap_or(0);
+        let mut pages: Vec<u8> = entries
+            .iter()
+            .filter(|(b, _)| b.len() == 2 && !index.contains_key(&b[..1]))
+            .map(|(b, _)| b[0])
+            .collect();
+        pages.dedup();
+
+        CharTable {
+            entries,
+            index,
+            canonical,
+            max_key_len,
+            pages,
+        }
+    }
+
+    // Final Fantasy II (US).
+    pub fn us() -> CharTable {
+        CharTable::new(
+            (0..=0xff)
+                .filter_map(|b| decode_byte(b).map(|s| (vec![b], s)))
+                .collect(),
+        )
+    }
+
+    // The built in table for `release`, if there is one.  The Japanese
+    // releases have none yet: the kana, marks and kanji pages 0x1e and 0x1f
+    // need checking against a JP dump before a table is built in.  Until then
+    // their text can be decoded with a `.tbl` loaded through `from_tbl`.
+    pub fn for_release(release: Release) -> Option<CharTable> {
+        match release {
+            Release::Ff2UsV10 | Release::Ff2UsV11 => Some(CharTable::us()),
+            Release::Ff4Jp | Release::Ff4JpEasyType => None,
+        }
+    }
+
+    // Adds the entries of `other`, replacing any for the same bytes.
+    pub fn extend(&mut self, other: &CharTable) {
+        let mut entries = self.entries.clone();
+        entries.extend(other.entries.iter().cloned());
+        let pages = [&self.pages[..], &other.pages[..]].concat();
+        *self = CharTable::new(entries);
+        self.add_pages(&pages);
+    }
+
+    // Whether `b` starts a two byte code.
+    pub fn is_page(&self, b: u8) -> bool {
+        self.pages.contains(&b)
+    }
+
+    fn add_pages(&mut self, pages: &[u8]) {
+        self.pages.extend_from_slice(pages);
+        self.pages.sort();
+        self.pages.dedup();
+    }
+
+    // Parses a Thingy style table: one `XX=text` line per entry where `XX` is
+    // one or more hex bytes.  `/XX` marks an end token, which decodes to
+    // nothing unless it has text, and `*XX` a line break.  Text runs to the end
+    // of the line so trailing spaces are kept.  Errors report the offset of
+    // the bad line in `tbl`.
+    pub fn from_tbl(tbl: &str) -> Result<CharTable, Error> {
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        for (i, raw) in tbl.split('\n').enumerate() {
+            let line = raw.trim_end_matches('\r').trim_start_matches('\u{feff}');
+            if !line.trim().is_empty() {
+                let entry = parse_tbl_line(line).ok_or(Error::BadTblEntry {
+                    table: "tbl",
+                    offset,
+                    line: i + 1,
+                })?;
+                entries.push(entry);
+            }
+            offset += raw.len() + 1;
+        }
+
+        Ok(CharTable::new(entries))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<CharTable> {
+        CharTable::from_tbl(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    // Writes the table in the format `from_tbl` reads.
+    pub fn to_tbl(&self) -> String {
+        let mut tbl = String::new();
+        for (bytes, text) in &self.entries {
+            let hex: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+            match text.as_str() {
+                "" => tbl.push_str(&format!("/{}\n", hex)),
+                "\n" => tbl.push_str(&format!("*{}\n", hex)),
+                _ => tbl.push_str(&format!("{}={}\n", hex, text)),
+            }
+        }
+        tbl
+    }
+
+    // The text `bytes` decodes to, if the table has an entry for them.
+    pub fn text(&self, bytes: &[u8]) -> Option<&str> {
+        self.index.get(bytes).map(|&i| self.entries[i].1.as_str())
+    }
+
+    // The longest entry at the start of `data` as (bytes, text).
+    fn lookup(&self, data: &[u8]) -> Option<(&[u8], &str)> {
+        (1..=self.max_key_len.min(data.len()))
+            .rev()
+            .find_map(|len| self.index.get(&data[..len]))
+            .map(|&i| (self.entries[i].0.as_slice(), self.entries[i].1.as_str()))
+    }
+
+    // Errors report offsets relative to `data`.
+    pub fn decode(&self, data: &[u8]) -> Result<String, Error> {
+        let mut s = String::from("");
+
+        let mut offset = 0;
+        while offset < data.len() {
+            match self.lookup(&data[offset..]) {
+                Some((bytes, text)) => {
+                    self.push_text(&mut s, text);
+                    offset += bytes.len();
+                }
+                None => {
+                    return Err(Error::UnknownTextByte {
+                        table: "text",
+                        offset,
+                        byte: data[offset],
+                    })
+                }
+            }
+        }
+
+        Ok(s)
+    }
+
+    // Like `decode` but never fails.  Every entry becomes a single character
+    // or escape: bytes with no text, and DTE bytes which would otherwise be
+    // indistinguishable from their two letters, are written as `<xx>`.
+    // `encode_lossless` turns the result back into the original bytes.
+    pub fn decode_lossless(&self, data: &[u8]) -> String {
+        self.decode_with(data, false)
+    }
+
+    // Like `decode` but never fails: DTE pairs are expanded and only bytes
+    // with no text are written as `<xx>`.  This is for showing text, since
+    // the result doesn't always encode back to `data`.
+    pub fn decode_display(&self, data: &[u8]) -> String {
+        self.decode_with(data, true)
+    }
+
+    fn decode_with(&self, data: &[u8], dte: bool) -> String {
+        let mut s = String::from("");
+
+        let mut offset = 0;
+        while offset < data.len() {
+            match self.lookup(&data[offset..]) {
+                Some((bytes, text)) if dte || self.is_canonical(bytes, text) => {
+                    self.push_text(&mut s, text);
+                    offset += bytes.len();
+                }
+                // Keep both bytes of a kanji without a glyph together.
+                None if self.pages.contains(&data[offset]) && offset + 1 < data.len() => {
+                    s.push_str(&format!("<{:02x}><{:02x}>", data[offset], data[offset + 1]));
+                    offset += 2;
+                }
+                _ => {
+                    s.push_str(&format!("<{:02x}>", data[offset]));
+                    offset += 1;
+                }
+            }
+        }
+
+        s
+    }
+
+    // Appends `text`, combining a dakuten or handakuten with the kana before
+    // it unless the table has its own entry for the voiced kana.
+    fn push_text(&self, s: &mut String, text: &str) {
+        let mut chars = text.chars();
+        if let (Some(mark), None, Some(kana)) = (chars.next(), chars.next(), s.chars().last()) {
+            if let Some(&voiced) = VOICED.get(&(kana, mark)) {
+                if self.canonical_bytes(voiced).is_none() {
+                    s.pop();
+                    s.push(voiced);
+                    return;
+                }
+            }
+        }
+        s.push_str(text);
+    }
+
+    fn canonical_bytes(&self, c: char) -> Option<&[u8]> {
+        self.canonical
+            .get(&c.to_string())
+            .map(|&i| self.entries[i].0.as_slice())
+    }
+
+    fn is_canonical(&self, bytes: &[u8], text: &str) -> bool {
+        self.canonical
+            .get(text)
+            .is_some_and(|&i| self.entries[i].0 == bytes)
+    }
+
+    // Encodes `text` in as few bytes as possible.  Each position considers
+    // every entry whose text matches there, so DTE pairs are picked to
+    // minimize the total rather than greedily.  `<xx>` escapes encode to the
+    // byte `xx`.  Errors report offsets relative to `text`.
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, Error> {
+        self.encode_with(text, true)
+    }
+
+    // Encodes each character or escape of `text` as a single entry.  This is
+    // the inverse of `decode_lossless`.
+    pub fn encode_lossless(&self, text: &str) -> Result<Vec<u8>, Error> {
+        self.encode_with(text, false)
+    }
+
+    // Encodes `text` one character per entry and pads it with spaces to
+    // `width` bytes.  Name tables don't use DTE, which keeps a decoded name
+    // encoding back to its original bytes.
+    pub fn encode_fixed(&self, text: &str, width: usize) -> Result<Vec<u8>, Error> {
+        let mut data = self.encode_lossless(text)?;
+        if data.len() > width {
+            return Err(Error::TableOverflow {
+                table: "text",
+                offset: 0,
+                len: data.len(),
+                capacity: width,
+            });
+        }
+        let space = match self.encode_lossless(" ")?.as_slice() {
+            &[b] => b,
+            _ => {
+                return Err(Error::UnencodableChar {
+                    table: "text",
+                    offset: 0,
+                    c: ' ',
+                })
+            }
+        };
+        data.resize(width, space);
+
+        Ok(data)
+    }
+
+    // Returns the bytes and text length of each entry which could encode the
+    // start of `text`.
+    fn candidates(&self, text: &str, dte: bool) -> Vec<(Vec<u8>, usize)> {
+        let mut candidates: Vec<(Vec<u8>, usize)> = self
+            .entries
+            .iter()
+            .filter(|(_, s)| !s.is_empty() && text.starts_with(s.as_str()))
+            .filter(|(b, s)| dte || self.is_canonical(b, s))
+            .map(|(b, s)| (b.clone(), s.len()))
+            .collect();
+        if let Some(b) = hex_escape(text) {
+            candidates.push((vec![b], 4));
+        }
+        // Voiced kana without their own entry are split back into the kana
+        // and its mark.
+        if let Some(c) = text.chars().next() {
+            if let (Some(&(kana, mark)), None) = (UNVOICED.get(&c), self.canonical_bytes(c)) {
+                if let (Some(kana), Some(mark)) =
+                    (self.canonical_bytes(kana), self.canonical_bytes(mark))
+                {
+                    candidates.push(([kana, mark].concat(), c.len_utf8()));
+                }
+            }
+        }
+        candidates
+    }
+
+    fn encode_with(&self, text: &str, dte: bool) -> Result<Vec<u8>, Error> {
+        // best[i] is the shortest encoding of `text[i..]` as (length, bytes,
+        // next).
+        let mut best: Vec<Option<(usize, Vec<u8>, usize)>> = vec![None; text.len() + 1];
+        best[text.len()] = Some((0, Vec::new(), text.len()));
+        for (i, _) in text.char_indices().rev() {
+            for (bytes, len) in self.candidates(&text[i..], dte) {
+                let next = i + len;
+                let total = match best[next] {
+                    Some((total, _, _)) => total + bytes.len(),
+                    None => continue,
+                };
+                if best[i]
+                    .as_ref()
+                    .is_none_or(|&(best_total, _, _)| total < best_total)
+                {
+                    best[i] = Some((total, bytes, next));
+                }
+            }
+        }
+
+        let mut data = Vec::new();
+        let mut i = 0;
+        while i < text.len() {
+            match best[i] {
+                Some((_, ref bytes, next)) => {
+                    data.extend_from_slice(bytes);
+                    i = next;
+                }
+                None => {
+                    // Every position something matches at can reach the end
+                    // so the first one without a match is the culprit.
+                    let (offset, c) = text
+                        .char_indices()
+                        .find(|&(j, _)| self.candidates(&text[j..], dte).is_empty())
+                        .unwrap();
+                    return Err(Error::UnencodableChar {
+                        table: "text",
+                        offset,
+                        c,
+                    });
+                }
+            }
+        }
+
+        Ok(data)
+    }
+}
 
-    for &b in data {
-        if 0x42 <= b && b <= 0x5b {
-            s.push((b - 0x42 + ('A' as u8)).into());
-        } else if 0x5c <= b && b <= 0x75 {
-            s.push((b - 0x5c + ('a' as u8)).into());
-        } else if 0x80 <= b && b <= 0x89 {
-            s.push((b - 0x80 + ('0' as u8)).into());
-        } else if SPECIAL_CHARS.contains_key(&b) {
-            s.push_str(SPECIAL_CHARS[&b]);
-        } else {
-            bail!("unknown char {:x}", b);
+// Parses one non-blank `.tbl` line into (bytes, text).
+fn parse_tbl_line(line: &str) -> Option<(Vec<u8>, String)> {
+    let (hex, text) = match line.chars().next()? {
+        '/' => {
+            let line = &line[1..];
+            match line.find('=') {
+                Some(i) => (&line[..i], line[i + 1..].to_string()),
+                None => (line, String::new()),
+            }
+        }
+        '*' => (&line[1..], "\n".to_string()),
+        _ => {
+            let i = line.find('=')?;
+            (&line[..i], line[i + 1..].to_string())
         }
+    };
+    let hex = hex.trim();
+    if hex.is_empty() || hex.len() % 2 != 0 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
     }
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect();
+
+    Some((bytes, text))
+}
 
-    Ok(s)
+// Single characters and escapes like `<mute>` can be encoded one entry at a
+// time.  Anything longer is DTE.
+fn is_single(text: &str) -> bool {
+    text.chars().count() == 1 || (text.len() > 2 && text.starts_with('<') && text.ends_with('>'))
+}
+
+// Parses a `<xx>` hex escape at the start of `text`.
+fn hex_escape(text: &str) -> Option<u8> {
+    let escape = text.get(..4)?;
+    if !escape.starts_with('<') || !escape.ends_with('>') {
+        return None;
+    }
+    let hex = &escape[1..3];
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    u8::from_str_radix(hex, 16).ok()
+}
+
+// The functions below use the US table.
+
+// Errors report offsets relative to `data`.
+pub fn decode(data: &[u8]) -> Result<String, Error> {
+    US.decode(data)
+}
+
+pub fn decode_lossless(data: &[u8]) -> String {
+    US.decode_lossless(data)
+}
+
+pub fn decode_display(data: &[u8]) -> String {
+    US.decode_display(data)
+}
+
+pub fn encode(text: &str) -> Result<Vec<u8>, Error> {
+    US.encode(text)
+}
+
+pub fn encode_lossless(text: &str) -> Result<Vec<u8>, Error> {
+    US.encode_lossless(text)
+}
+
+pub fn encode_fixed(text: &str, width: usize) -> Result<Vec<u8>, Error> {
+    US.encode_fixed(text, width)
 }
 
 #[cfg(test)]
@@ -173,6 +639,173 @@ mod tests {
             "Naga    ",
             decode(&[0x4f, 0x5c, 0x62, 0x5c, 0xff, 0xff, 0xff, 0xff]).unwrap()
         );
+        assert_eq!(
+            Err(Error::UnknownTextByte {
+                table: "text",
+                offset: 1,
+                byte: 0x00,
+            }),
+            decode(&[0x4f, 0x00])
+        );
+    }
+
+    #[test]
+    fn encode_test() {
+        assert_eq!(
+            vec!(0x4f, 0x5c, 0x62, 0x5c, 0xce, 0xce),
+            encode("Naga    ").unwrap()
+        );
+        assert_eq!(
+            vec!(0x50, 0x80, 0xc1, 0x25, 0x76, 0xc3),
+            encode("O0.<mute><flat m>…").unwrap()
+        );
+        // Eight characters fit in five bytes using three DTE pairs.
+        let encoded = encode("The call").unwrap();
+        assert_eq!(5, encoded.len());
+        assert_eq!("The call", decode(&encoded).unwrap());
+        assert_eq!(
+            Err(Error::UnencodableChar {
+                table: "text",
+                offset: 3,
+                c: '<',
+            }),
+            encode("Imp<bad>")
+        );
+        assert!(encode("~").is_err());
+    }
+
+    #[test]
+    fn lossless_test() {
+        assert_eq!(
+            "Imp<00><8b><1c> ",
+            decode_lossless(&[0x4a, 0x68, 0x6b, 0x00, 0x8b, 0x1c, 0xff])
+        );
+        assert_eq!(vec!(0x00, 0x7f), encode("<00><7F>").unwrap());
+
+        let all: Vec<u8> = (0..=0xff).collect();
+        assert_eq!(all, encode_lossless(&decode_lossless(&all)).unwrap());
+        // DTE bytes and the letters they expand to stay distinct.
+        assert_eq!(
+            vec!(0x8b, 0x73, 0x63),
+            encode_lossless(&decode_lossless(&[0x8b, 0x73, 0x63])).unwrap()
+        );
+
+        // Display text expands DTE pairs but still escapes unknown bytes.
+        assert_eq!(
+            "Imp<00>the ",
+            decode_display(&[0x4a, 0x68, 0x6b, 0x00, 0x8b, 0x60, 0xff])
+        );
     }
 
+    #[test]
+    fn encode_fixed_test() {
+        let naga = [0x4f, 0x5c, 0x62, 0x5c, 0xff, 0xff, 0xff, 0xff];
+        assert_eq!(naga.to_vec(), encode_fixed("Naga", 8).unwrap());
+        assert_eq!(
+            naga.to_vec(),
+            encode_fixed(&decode(&naga).unwrap(), 8).unwrap()
+        );
+        assert_eq!(
+            vec!(0x5d, 0x5c, 0x5d, 0x5c, 0xff, 0xff),
+            encode_fixed("baba", 6).unwrap()
+        );
+        assert_eq!(
+            Err(Error::TableOverflow {
+                table: "text",
+                offset: 0,
+                len: 9,
+                capacity: 8,
+            }),
+            encode_fixed("Too long!", 8)
+        );
+    }
+
+    #[test]
+    fn tbl_test() {
+        let us = CharTable::us();
+        assert_eq!(Some("th"), us.text(&[0x8b]));
+        assert_eq!(us, CharTable::from_tbl(&us.to_tbl()).unwrap());
+        assert!(us.to_tbl().contains("\nFF= \n"));
+
+        let tbl = "\u{feff}01=a\r\n\n02=b=\n0102=ab!\n/00\n*FE\n03=  \n";
+        let table = CharTable::from_tbl(tbl).unwrap();
+        assert_eq!(Some("b="), table.text(&[0x02]));
+        assert_eq!(Some("  "), table.text(&[0x03]));
+        // The longest entry wins when decoding.
+        assert_eq!(
+            "ab!a\n",
+            table.decode(&[0x01, 0x02, 0x01, 0xfe, 0x00]).unwrap()
+        );
+        assert_eq!(vec!(0x01, 0x02, 0x03), table.encode("ab!  ").unwrap());
+        assert_eq!(
+            vec!(0x01, 0x02, 0x01, 0xfe, 0x00),
+            table
+                .encode_lossless(&table.decode_lossless(&[0x01, 0x02, 0x01, 0xfe, 0x00]))
+                .unwrap()
+        );
+        assert_eq!(table, CharTable::from_tbl(&table.to_tbl()).unwrap());
+        assert_eq!(
+            Err(Error::UnencodableChar {
+                table: "text",
+                offset: 0,
+                c: ' ',
+            }),
+            table.encode_fixed("a", 4)
+        );
+
+        assert_eq!(
+            Err(Error::BadTblEntry {
+                table: "tbl",
+                offset: 5,
+                line: 2,
+            }),
+            CharTable::from_tbl("01=a\n1=b\n")
+        );
+        assert!(CharTable::from_tbl("0g=a").is_err());
+        assert!(CharTable::from_tbl("01").is_err());
+    }
+
+    #[test]
+    fn kana_test() {
+        // A few entries laid out like a Japanese font.
+        let kana = CharTable::from_tbl(
+            "49=H\n51=P\n81=1\n82=2\n8A=あ\nA3=は\nBA=ウ\nC1=コ\nD3=フ\nDF=リ\nE5=ン\nF8=゛\nF9=゜\n",
+        )
+        .unwrap();
+        // ゴブリン is written コ゛フ゛リン.
+        let goblin = [0xc1, 0xf8, 0xd3, 0xf8, 0xdf, 0xe5];
+        assert_eq!("ゴブリン", kana.decode(&goblin).unwrap());
+        assert_eq!(goblin.to_vec(), kana.encode("ゴブリン").unwrap());
+        assert_eq!("ぱヴ", kana.decode(&[0xa3, 0xf9, 0xba, 0xf8]).unwrap());
+        // Marks which don't combine are left alone.
+        assert_eq!(
+            "あ゛HP12",
+            kana.decode(&[0x8a, 0xf8, 0x49, 0x51, 0x81, 0x82]).unwrap()
+        );
+        assert_eq!(
+            vec!(0x8a, 0xf8),
+            kana.encode_lossless(&kana.decode_lossless(&[0x8a, 0xf8]))
+                .unwrap()
+        );
+
+        // Two byte entries make their first byte a page, so codes on the
+        // page without a glyph keep both bytes together.
+        let mut with_kanji = kana.clone();
+        with_kanji.extend(&CharTable::from_tbl("1E8A=剣\n").unwrap());
+        let kanji = [0x1e, 0x8a, 0xa3, 0xf9];
+        assert_eq!("剣ぱ", with_kanji.decode(&kanji).unwrap());
+        assert_eq!(kanji.to_vec(), with_kanji.encode("剣ぱ").unwrap());
+        assert_eq!(
+            "<1e><8b>ぱ",
+            with_kanji.decode_lossless(&[0x1e, 0x8b, 0xa3, 0xf9])
+        );
+        assert_eq!(
+            vec!(0x1e, 0x8b),
+            with_kanji
+                .encode_lossless(&with_kanji.decode_lossless(&[0x1e, 0x8b]))
+                .unwrap()
+        );
+
+        assert_eq!(None, CharTable::for_release(Release::Ff4JpEasyType));
+    }
 }
@@ -1,15 +1,12 @@
 use std::error::Error;
-use std::fs::File;
-use std::io::Read;
 use std::path::PathBuf;
 
-pub fn load_rom() -> Result<Vec<u8>, Box<Error>> {
+use super::rom::Rom;
+
+// Loads the test ROM with any copier header stripped.
+pub fn load_rom() -> Result<Vec<u8>, Box<dyn Error>> {
     let mut p = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     p.push("ff2us.smc");
 
-    let mut f = File::open(p)?;
-    let mut data = Vec::new();
-    f.read_to_end(&mut data)?;
-
-    Ok(data)
+    Ok(Rom::load(p)?.data)
 }